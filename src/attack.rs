@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+use crate::collision::Hitbox;
+use crate::dodge::Dodging;
+use crate::guard::Guarding;
+use crate::jump::Jumping;
+use crate::physics::Knockback;
+use crate::player::{
+    ActionSet, Character, Controller, Controller1, Controller2, Movement, ReadInputSet, Ready,
+};
+use crate::sprite_sheet::{AnimationEnded, Clip, NoRepeat};
+use crate::{input, GameState};
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+
+pub struct AttackPlugin;
+
+/// This plugin handles the light attack combo chain
+/// A press during the cancel window of a swing chains into the next one
+impl Plugin for AttackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (attack_input::<Controller1>, attack_input::<Controller2>)
+                .in_set(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (attack, strike_frames, end_attack, combo_timeout)
+                .chain()
+                .in_set(ActionSet)
+                .after(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// One attack move, a swing of a combo chain or a standalone attack
+#[derive(Clone)]
+pub struct AttackStep {
+    pub clip: Clip,
+    /// First frame from which an attack press chains into the next step
+    pub cancel: usize,
+    /// First and last frame with the hitbox out
+    pub active: (usize, usize),
+    pub hitbox: Rect,
+    pub depth: f32,
+    pub strike: Strike,
+}
+
+/// What a hitbox does to its victims, lives next to the [`Hitbox`] while it is out
+#[derive(Component, Clone)]
+pub struct Strike {
+    pub damage: f32,
+    /// Milliseconds the victim can't act
+    pub hitstun: u64,
+    /// Floor velocity given to the victim, X points away from the attacker
+    pub knockback: Vec2,
+    /// Vertical velocity given to the victim, it is knocked down when it lands
+    pub launch: f32,
+    /// Milliseconds a launched victim stays down
+    pub knockdown: u64,
+}
+
+#[derive(Component, Clone)]
+pub struct AttackChain {
+    pub steps: Vec<AttackStep>,
+    /// How long the chain waits for the next press after a swing ended
+    pub reset: Duration,
+}
+
+/// Attack done instead of the chain when attacking out of a run
+#[derive(Component, Clone)]
+pub struct RunAttack {
+    pub attack: AttackStep,
+    /// Speed the character keeps sliding forward with
+    pub slide: f32,
+}
+
+/// Attack done instead of the chain when attacking out of a guard
+#[derive(Component, Clone)]
+pub struct GuardAttack(pub AttackStep);
+
+/// Attack done instead of the chain when attacking in the air, once per jump
+#[derive(Component, Clone)]
+pub struct JumpAttack(pub AttackStep);
+
+/// Intent to attack, set by a controller and consumed by [`attack`]
+#[derive(Component)]
+pub struct WantsAttack;
+
+#[derive(Component)]
+pub struct Attacking {
+    pub attack: AttackStep,
+    /// Index in the [`AttackChain`], none for attacks outside of it
+    pub step: Option<usize>,
+}
+
+impl Attacking {
+    /// Plays an attack on the character, replacing the one it was doing
+    pub fn start(entity: &mut EntityCommands, attack: AttackStep, step: Option<usize>) {
+        entity
+            .remove::<(Combo, Hitbox, Strike)>()
+            .insert(attack.clip.bundle())
+            .insert((Attacking { attack, step }, NoRepeat));
+    }
+}
+
+/// Everything an attack puts on its character, removed when the attack is interrupted
+pub type AttackState = (Attacking, Combo, Hitbox, Strike);
+
+/// Keeps the chain alive between two swings
+#[derive(Component)]
+pub struct Combo {
+    next: usize,
+    timer: Timer,
+}
+
+/// Asks to attack as long as a buffered press wasn't used by an attack
+fn attack_input<C: Controller>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut buffer: ResMut<input::InputBuffer<C>>,
+    players: Query<
+        (Entity, Option<Ref<Attacking>>),
+        (With<C>, With<Character>, Ready, Without<Dodging>),
+    >,
+) {
+    for (e, attacking) in &players {
+        if attacking.is_some_and(|attacking| attacking.is_changed()) {
+            buffer.consume(input::Press::Attack, time.elapsed());
+        }
+        if buffer.buffered(input::Press::Attack, time.elapsed()) {
+            commands.entity(e).insert(WantsAttack);
+        }
+    }
+}
+
+pub fn attack(
+    mut commands: Commands,
+    players: Query<
+        (
+            Entity,
+            &AttackChain,
+            Option<&Attacking>,
+            Option<&Combo>,
+            Option<&TextureAtlas>,
+        ),
+        With<WantsAttack>,
+    >,
+    moves: Query<(
+        &Movement,
+        &Sprite,
+        Has<Guarding>,
+        Option<&RunAttack>,
+        Option<&GuardAttack>,
+    )>,
+    mut air: Query<(&mut Jumping, Option<&JumpAttack>)>,
+) {
+    for (e, chain, attacking, combo, atlas) in &players {
+        commands.entity(e).remove::<WantsAttack>();
+
+        if let Ok((mut jumping, jump_attack)) = air.get_mut(e) {
+            if let (false, Some(JumpAttack(attack))) = (jumping.attacked, jump_attack) {
+                jumping.attacked = true;
+                Attacking::start(&mut commands.entity(e), attack.clone(), None);
+            }
+            continue;
+        }
+
+        if let (None, Ok((&movement, sprite, guarding, run_attack, guard_attack))) =
+            (attacking, moves.get(e))
+        {
+            if guarding {
+                if let Some(GuardAttack(attack)) = guard_attack {
+                    let mut entity = commands.entity(e);
+                    entity.remove::<Guarding>();
+                    Attacking::start(&mut entity, attack.clone(), None);
+                }
+                continue;
+            }
+            if let (Movement::Run, Some(run_attack)) = (movement, run_attack) {
+                let facing = if sprite.flip_x { -1. } else { 1. };
+                let mut entity = commands.entity(e);
+                entity.insert(Knockback(Vec2::new(facing * run_attack.slide, 0.)));
+                Attacking::start(&mut entity, run_attack.attack.clone(), None);
+                continue;
+            }
+        }
+
+        let step = match attacking {
+            Some(Attacking {
+                attack,
+                step: Some(step),
+            }) => {
+                let frame = atlas.map(|a| a.index).unwrap_or_default();
+                if step + 1 >= chain.steps.len() || frame < attack.cancel {
+                    continue;
+                }
+                step + 1
+            }
+            Some(_) => continue,
+            None => combo.map(|c| c.next).unwrap_or(0),
+        };
+
+        Attacking::start(
+            &mut commands.entity(e),
+            chain.steps[step].clone(),
+            Some(step),
+        );
+    }
+}
+
+fn strike_frames(
+    mut commands: Commands,
+    players: Query<(Entity, &Attacking, &TextureAtlas, Option<&Hitbox>)>,
+) {
+    for (e, attacking, atlas, hitbox) in &players {
+        let step = &attacking.attack;
+        let (first, last) = step.active;
+        if atlas.index < first {
+            continue;
+        }
+        if atlas.index > last {
+            if hitbox.is_some() {
+                commands.entity(e).remove::<(Hitbox, Strike)>();
+            }
+        } else if hitbox.is_none() && atlas.index == first {
+            commands
+                .entity(e)
+                .insert((Hitbox::new(step.hitbox, step.depth), step.strike.clone()));
+        }
+    }
+}
+
+fn end_attack(
+    mut commands: Commands,
+    mut ended: EventReader<AnimationEnded>,
+    players: Query<(&Attacking, Option<&AttackChain>, Has<Jumping>)>,
+) {
+    for &AnimationEnded(e) in ended.read() {
+        let Ok((attacking, chain, jumping)) = players.get(e) else {
+            continue;
+        };
+        // holds the last frame until landing
+        if jumping {
+            commands.entity(e).remove::<(Attacking, Hitbox, Strike)>();
+            continue;
+        }
+
+        commands
+            .entity(e)
+            .remove::<(Attacking, NoRepeat, Hitbox, Strike)>()
+            .insert(Movement::Idle);
+
+        let (Some(step), Some(chain)) = (attacking.step, chain) else {
+            continue;
+        };
+        if step + 1 < chain.steps.len() {
+            commands.entity(e).insert(Combo {
+                next: step + 1,
+                timer: Timer::new(chain.reset, TimerMode::Once),
+            });
+        }
+    }
+}
+
+fn combo_timeout(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut combos: Query<(Entity, &mut Combo), Without<Attacking>>,
+) {
+    for (e, mut combo) in &mut combos {
+        if combo.timer.tick(time.delta()).finished() {
+            commands.entity(e).remove::<Combo>();
+        }
+    }
+}
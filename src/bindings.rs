@@ -0,0 +1,415 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::input::{self, RunMode, Socd};
+use crate::player::{Controller, Controller1, Controller2};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct BindingsPlugin;
+
+/// This plugin loads the key bindings of both players from the config file, or uses the defaults
+/// The input entities of the players are spawned from them when a game starts
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load());
+    }
+}
+
+const CONFIG_DIR: &str = "peakr";
+const CONFIG_FILE: &str = "bindings.ron";
+
+/// A keyboard key or a mouse button
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{key:?}");
+                let name = name
+                    .strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Digit"))
+                    .unwrap_or(&name);
+                write!(f, "{name}")
+            }
+            Binding::Mouse(button) => write!(f, "Mouse {button:?}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Run,
+    Attack,
+    Guard,
+    Dodge,
+    Jump,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Run,
+        Action::Attack,
+        Action::Guard,
+        Action::Dodge,
+        Action::Jump,
+    ];
+
+    /// Movement is read as an analog from keys only
+    pub fn takes_mouse(self) -> bool {
+        !matches!(
+            self,
+            Action::Up | Action::Down | Action::Left | Action::Right
+        )
+    }
+
+    /// Movement is read from the left stick
+    pub fn takes_pad(self) -> bool {
+        !matches!(
+            self,
+            Action::Up | Action::Down | Action::Left | Action::Right
+        )
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Gamepad buttons of one player
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PadBindings {
+    pub run: GamepadButtonType,
+    pub attack: GamepadButtonType,
+    pub guard: GamepadButtonType,
+    pub dodge: GamepadButtonType,
+    pub jump: GamepadButtonType,
+}
+
+impl Default for PadBindings {
+    fn default() -> Self {
+        PadBindings {
+            run: GamepadButtonType::RightTrigger2,
+            attack: GamepadButtonType::West,
+            guard: GamepadButtonType::RightTrigger,
+            dodge: GamepadButtonType::East,
+            jump: GamepadButtonType::South,
+        }
+    }
+}
+
+impl PadBindings {
+    /// None for movement, it is read from the stick
+    pub fn get(&self, action: Action) -> Option<GamepadButtonType> {
+        match action {
+            Action::Run => Some(self.run),
+            Action::Attack => Some(self.attack),
+            Action::Guard => Some(self.guard),
+            Action::Dodge => Some(self.dodge),
+            Action::Jump => Some(self.jump),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, action: Action, button: GamepadButtonType) {
+        match action {
+            Action::Run => self.run = button,
+            Action::Attack => self.attack = button,
+            Action::Guard => self.guard = button,
+            Action::Dodge => self.dodge = button,
+            Action::Jump => self.jump = button,
+            _ => {}
+        }
+    }
+
+    /// Binds the action, an action already using the button gets the one it replaces
+    /// Returns that action, pads belong to one player so only its own actions conflict
+    pub fn rebind(&mut self, action: Action, button: GamepadButtonType) -> Option<Action> {
+        let previous = self.get(action)?;
+        let other = Action::ALL
+            .into_iter()
+            .find(|&other| other != action && self.get(other) == Some(button));
+        if let Some(other) = other {
+            self.set(other, previous);
+        }
+        self.set(action, button);
+        other
+    }
+}
+
+/// Double tap windows the controls screen goes through, in milliseconds
+pub const DOUBLE_TAP_WINDOWS: [u64; 5] = [150, 200, 250, 300, 400];
+
+fn default_double_tap() -> u64 {
+    250
+}
+
+/// Keyboard, mouse and gamepad bindings of one player
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PlayerBindings {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub run: Binding,
+    pub attack: Binding,
+    pub guard: Binding,
+    pub dodge: Binding,
+    pub jump: Binding,
+    #[serde(default)]
+    pub pad: PadBindings,
+    /// What opposite movement keys held together do
+    #[serde(default)]
+    pub socd: Socd,
+    #[serde(default)]
+    pub run_mode: RunMode,
+    /// Milliseconds allowed between each tap of a [`RunMode::DoubleTap`] run and the let go in between
+    #[serde(default = "default_double_tap")]
+    pub double_tap: u64,
+}
+
+impl PlayerBindings {
+    pub fn get(&self, action: Action) -> Binding {
+        match action {
+            Action::Up => Binding::Key(self.up),
+            Action::Down => Binding::Key(self.down),
+            Action::Left => Binding::Key(self.left),
+            Action::Right => Binding::Key(self.right),
+            Action::Run => self.run,
+            Action::Attack => self.attack,
+            Action::Guard => self.guard,
+            Action::Dodge => self.dodge,
+            Action::Jump => self.jump,
+        }
+    }
+
+    /// Binds the action, mouse buttons are ignored for movement
+    pub fn set(&mut self, action: Action, binding: Binding) {
+        let key = match binding {
+            Binding::Key(key) => Some(key),
+            Binding::Mouse(_) => None,
+        };
+        match (action, key) {
+            (Action::Up, Some(key)) => self.up = key,
+            (Action::Down, Some(key)) => self.down = key,
+            (Action::Left, Some(key)) => self.left = key,
+            (Action::Right, Some(key)) => self.right = key,
+            (Action::Run, _) => self.run = binding,
+            (Action::Attack, _) => self.attack = binding,
+            (Action::Guard, _) => self.guard = binding,
+            (Action::Dodge, _) => self.dodge = binding,
+            (Action::Jump, _) => self.jump = binding,
+            _ => {}
+        }
+    }
+
+    /// The next of the [`DOUBLE_TAP_WINDOWS`], back to the first after the last
+    pub fn next_double_tap(&self) -> u64 {
+        DOUBLE_TAP_WINDOWS
+            .into_iter()
+            .find(|&window| window > self.double_tap)
+            .unwrap_or(DOUBLE_TAP_WINDOWS[0])
+    }
+
+    /// Spawns the keyboard, mouse and gamepad inputs of a controller
+    pub fn spawn<C: Controller>(&self, commands: &mut Commands) {
+        commands.spawn((
+            C::default(),
+            input::Input,
+            input::RunSwitch {
+                mode: self.run_mode,
+                double_tap: Duration::from_millis(self.double_tap),
+            },
+        ));
+        commands.spawn((
+            C::default(),
+            input::Input,
+            input::Analog(0., 0.),
+            input::Movement,
+            input::KeyboardAnalog::new(self.up, self.down, self.right, self.left, self.socd),
+        ));
+        spawn_action::<C>(commands, self.run, input::Run);
+        spawn_action::<C>(commands, self.attack, input::Attack);
+        spawn_action::<C>(commands, self.guard, input::Guard);
+        spawn_action::<C>(commands, self.dodge, input::Dodge);
+        spawn_action::<C>(commands, self.jump, input::Jump);
+
+        commands.spawn((
+            C::default(),
+            input::Input,
+            input::Pad::default(),
+            input::Analog(0., 0.),
+            input::Movement,
+            input::GamepadStickAnalog::left(),
+        ));
+        spawn_pad_action::<C>(commands, self.pad.run, input::Run);
+        spawn_pad_action::<C>(commands, self.pad.attack, input::Attack);
+        spawn_pad_action::<C>(commands, self.pad.guard, input::Guard);
+        spawn_pad_action::<C>(commands, self.pad.dodge, input::Dodge);
+        spawn_pad_action::<C>(commands, self.pad.jump, input::Jump);
+    }
+}
+
+fn spawn_action<C: Controller>(commands: &mut Commands, binding: Binding, action: impl Bundle) {
+    let mut input = commands.spawn((C::default(), input::Input, action));
+    match binding {
+        Binding::Key(key) => input.insert(input::KeyboardAction(key)),
+        Binding::Mouse(button) => input.insert(input::MouseAction(button)),
+    };
+}
+
+fn spawn_pad_action<C: Controller>(
+    commands: &mut Commands,
+    button: GamepadButtonType,
+    action: impl Bundle,
+) {
+    commands.spawn((
+        C::default(),
+        input::Input,
+        input::Pad::default(),
+        action,
+        input::GamepadButtonAction(button),
+    ));
+}
+
+/// Keyboard, mouse and gamepad bindings of both players, saved to the config file when they change
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Bindings {
+    pub players: [PlayerBindings; 2],
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            players: [
+                PlayerBindings {
+                    up: KeyCode::KeyW,
+                    down: KeyCode::KeyS,
+                    left: KeyCode::KeyA,
+                    right: KeyCode::KeyD,
+                    run: Binding::Key(KeyCode::ShiftLeft),
+                    attack: Binding::Mouse(MouseButton::Left),
+                    guard: Binding::Mouse(MouseButton::Right),
+                    dodge: Binding::Key(KeyCode::Space),
+                    jump: Binding::Key(KeyCode::KeyE),
+                    pad: PadBindings::default(),
+                    socd: Socd::LastInput,
+                    run_mode: RunMode::Hold,
+                    double_tap: default_double_tap(),
+                },
+                PlayerBindings {
+                    up: KeyCode::ArrowUp,
+                    down: KeyCode::ArrowDown,
+                    left: KeyCode::ArrowLeft,
+                    right: KeyCode::ArrowRight,
+                    run: Binding::Key(KeyCode::ShiftRight),
+                    attack: Binding::Key(KeyCode::Numpad1),
+                    guard: Binding::Key(KeyCode::Numpad2),
+                    dodge: Binding::Key(KeyCode::Numpad3),
+                    jump: Binding::Key(KeyCode::Numpad0),
+                    pad: PadBindings::default(),
+                    socd: Socd::LastInput,
+                    run_mode: RunMode::Hold,
+                    double_tap: default_double_tap(),
+                },
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    /// Spawns the inputs of both controllers
+    pub fn spawn(&self, commands: &mut Commands) {
+        self.players[0].spawn::<Controller1>(commands);
+        self.players[1].spawn::<Controller2>(commands);
+    }
+
+    /// Player and action the binding is already used for
+    pub fn conflict(&self, binding: Binding) -> Option<(usize, Action)> {
+        self.players
+            .iter()
+            .enumerate()
+            .find_map(|(player, bindings)| {
+                Action::ALL
+                    .into_iter()
+                    .find(|&action| bindings.get(action) == binding)
+                    .map(|action| (player, action))
+            })
+    }
+
+    /// Binds the action of the player, an action already using the binding gets the one it replaces
+    /// Fails when the conflicting action can't take the replaced binding
+    pub fn rebind(&mut self, player: usize, action: Action, binding: Binding) -> bool {
+        if binding == self.players[player].get(action) {
+            return true;
+        }
+        if let Binding::Mouse(_) = binding {
+            if !action.takes_mouse() {
+                return false;
+            }
+        }
+        let previous = self.players[player].get(action);
+        if let Some((other, other_action)) = self.conflict(binding) {
+            if let Binding::Mouse(_) = previous {
+                if !other_action.takes_mouse() {
+                    return false;
+                }
+            }
+            self.players[other].set(other_action, previous);
+        }
+        self.players[player].set(action, binding);
+        true
+    }
+
+    /// The bindings saved in the config file, the defaults when there are none
+    pub fn load() -> Bindings {
+        let Some(path) = config_path() else {
+            return Bindings::default();
+        };
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return Bindings::default();
+        };
+        ron::from_str(&text).unwrap_or_else(|err| {
+            warn!("ignoring bindings in {}: {err}", path.display());
+            Bindings::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let Some(path) = config_path() else {
+            return;
+        };
+        let saved = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|err| err.to_string())
+            .and_then(|text| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+                }
+                std::fs::write(&path, text).map_err(|err| err.to_string())
+            });
+        if let Err(err) = saved {
+            warn!("could not save bindings to {}: {err}", path.display());
+        }
+    }
+}
+
+/// Where the bindings are kept, in the config dir of the user
+fn config_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join(CONFIG_DIR).join(CONFIG_FILE))
+}
@@ -0,0 +1,138 @@
+use crate::encounter::ScreenLock;
+use crate::enemy::Enemy;
+use crate::player::{Alive, Character, LevelLimit};
+use crate::GameState;
+use bevy::{prelude::*, render::camera::ScalingMode};
+
+pub struct CameraPlugin;
+
+/// This plugin scrolls the camera along the stage, following the players
+/// The camera doesn't scroll back, stays within the stage and settles on the locked area during encounters
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraSettings>()
+            .init_resource::<CameraView>()
+            .add_systems(Startup, add_camera)
+            .add_systems(OnEnter(GameState::Playing), reset)
+            .add_systems(
+                PostUpdate,
+                follow
+                    .in_set(CameraSet)
+                    .run_if(resource_exists::<LevelLimit>)
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Moves the camera, systems placing things relative to the view run after it
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CameraSet;
+
+#[derive(Resource)]
+pub struct CameraSettings {
+    /// Distance the players can get from the center of the view before it scrolls
+    pub dead_zone: f32,
+    /// How fast the camera catches up with the players, higher is snappier
+    pub smoothing: f32,
+    /// How far back from the furthest point reached the camera may scroll
+    pub backtrack: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            dead_zone: 40.,
+            smoothing: 4.,
+            backtrack: 0.,
+        }
+    }
+}
+
+/// World area seen by the camera, none until the camera has been placed
+#[derive(Resource, Default)]
+pub struct CameraView(pub Option<Rect>);
+
+#[derive(Component)]
+pub struct GameCamera {
+    /// Furthest X the camera scrolled to
+    furthest: f32,
+}
+
+fn add_camera(mut commands: Commands) {
+    commands.spawn((
+        GameCamera {
+            furthest: f32::NEG_INFINITY,
+        },
+        Camera2dBundle {
+            transform: Transform::from_xyz(0.0, 0.0, 100.),
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::FixedVertical(270.),
+
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+fn reset(mut cameras: Query<(&mut Transform, &mut GameCamera)>, mut view: ResMut<CameraView>) {
+    for (mut t, mut camera) in &mut cameras {
+        t.translation.x = 0.;
+        camera.furthest = f32::NEG_INFINITY;
+    }
+    view.0 = None;
+}
+
+fn follow(
+    time: Res<Time>,
+    settings: Res<CameraSettings>,
+    lock: Res<ScreenLock>,
+    limit: Res<LevelLimit>,
+    mut view: ResMut<CameraView>,
+    mut cameras: Query<(&mut Transform, &mut GameCamera, &OrthographicProjection)>,
+    players: Query<
+        &Transform,
+        (
+            With<Character>,
+            With<Alive>,
+            Without<Enemy>,
+            Without<GameCamera>,
+        ),
+    >,
+) {
+    let Ok((mut t, mut camera, projection)) = cameras.get_single_mut() else {
+        return;
+    };
+    let half = projection.area.width() / 2.;
+
+    let x = t.translation.x;
+    let count = players.iter().count();
+    let goal = match lock.0 {
+        Some((left, right)) => Some((left + right) / 2.),
+        None if count > 0 => {
+            let middle = players.iter().map(|t| t.translation.x).sum::<f32>() / count as f32;
+            let goal = x + middle - middle.clamp(x - settings.dead_zone, x + settings.dead_zone);
+            Some(goal.max(camera.furthest - settings.backtrack))
+        }
+        None => None,
+    };
+
+    if let Some(goal) = goal {
+        let LevelLimit(bounds) = *limit;
+        let goal = if bounds.width() <= half * 2. {
+            bounds.center().x
+        } else {
+            goal.clamp(bounds.min.x + half, bounds.max.x - half)
+        };
+
+        let catch_up = 1. - (-settings.smoothing * time.delta_seconds()).exp();
+        t.translation.x += (goal - x) * catch_up;
+        camera.furthest = camera.furthest.max(t.translation.x);
+    }
+
+    view.0 = Some(Rect::from_corners(
+        projection.area.min + t.translation.truncate(),
+        projection.area.max + t.translation.truncate(),
+    ));
+}
@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::attack::{AttackChain, AttackStep, GuardAttack, JumpAttack, RunAttack, Strike};
+use crate::charge::{ChargeAttack, ChargeTier};
+use crate::collision::Hurtbox;
+use crate::dodge::DodgeRoll;
+use crate::enemy::Difficulty;
+use crate::guard::Guard;
+use crate::health::HurtClips;
+use crate::jump::Jump;
+use crate::motion::{Motion, Specials};
+use crate::player::{MoveSpeed, MovementClips};
+use crate::sprite_sheet::Clip;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Everything that makes a fighter, loaded from a `.character.ron` file by [`CharacterDefLoader`]
+#[derive(Asset, TypePath)]
+pub struct CharacterDef {
+    pub health: f32,
+    pub speed: MoveSpeed,
+    pub hurtbox: Hurtbox,
+    pub movement: MovementClips,
+    pub hurt: HurtClips,
+    pub attacks: AttackChain,
+    pub charge: Option<ChargeAttack>,
+    pub run_attack: Option<RunAttack>,
+    pub guard_attack: Option<GuardAttack>,
+    pub guard: Option<Guard>,
+    pub dodge: Option<DodgeRoll>,
+    pub jump: Option<Jump>,
+    pub jump_attack: Option<JumpAttack>,
+    pub specials: Specials,
+    /// How the character fights when it is an enemy
+    pub ai: Option<Difficulty>,
+}
+
+#[derive(Default)]
+pub struct CharacterDefLoader;
+
+#[derive(Debug, Error)]
+pub enum CharacterDefLoaderError {
+    #[error("could not read character file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse character file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid character file: {0}")]
+    Invalid(String),
+}
+
+impl AssetLoader for CharacterDefLoader {
+    type Asset = CharacterDef;
+    type Settings = ();
+    type Error = CharacterDefLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<CharacterDef, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: CharacterFile = ron::de::from_bytes(&bytes)?;
+        file.validate().map_err(CharacterDefLoaderError::Invalid)?;
+
+        let mut sheets = Sheets {
+            context: load_context,
+            tile: UVec2::new(file.tile_size.0, file.tile_size.1),
+            layouts: HashMap::new(),
+        };
+        Ok(CharacterDef {
+            health: file.health,
+            speed: MoveSpeed {
+                walk: file.speed.walk,
+                run: file.speed.run,
+            },
+            hurtbox: Hurtbox {
+                rect: rect(file.hurtbox.rect),
+                depth: file.hurtbox.depth,
+            },
+            movement: MovementClips {
+                idle: sheets.clip(&file.movement.idle),
+                walk: sheets.clip(&file.movement.walk),
+                run: sheets.clip(&file.movement.run),
+            },
+            hurt: HurtClips {
+                hurt: sheets.clip(&file.hurt.hurt),
+                dead: sheets.clip(&file.hurt.dead),
+                get_up: sheets.clip(&file.hurt.get_up),
+            },
+            attacks: AttackChain {
+                steps: file
+                    .attacks
+                    .steps
+                    .iter()
+                    .map(|step| sheets.attack(step))
+                    .collect(),
+                reset: Duration::from_millis(file.attacks.reset),
+            },
+            charge: file.charge.map(|charge| ChargeAttack {
+                stance: sheets.clip(&charge.stance),
+                walk: sheets.clip(&charge.walk),
+                release: sheets.attack(&charge.release),
+                delay: Duration::from_millis(charge.delay),
+                tiers: charge
+                    .tiers
+                    .iter()
+                    .map(|tier| ChargeTier {
+                        time: Duration::from_millis(tier.time),
+                        scale: tier.scale,
+                        color: Color::srgb(tier.color.0, tier.color.1, tier.color.2),
+                    })
+                    .collect(),
+            }),
+            run_attack: file.run_attack.map(|run| RunAttack {
+                attack: sheets.attack(&run.attack),
+                slide: run.slide,
+            }),
+            guard_attack: file
+                .guard_attack
+                .map(|attack| GuardAttack(sheets.attack(&attack))),
+            guard: file.guard.map(|guard| Guard {
+                clip: sheets.clip(&guard.clip),
+                dizzy_clip: sheets.clip(&guard.dizzy_clip),
+                chip: guard.chip,
+                meter: guard.meter,
+                regen: guard.regen,
+                dizzy: Duration::from_millis(guard.dizzy),
+            }),
+            dodge: file.dodge.map(|dodge| DodgeRoll {
+                clip: sheets.clip(&dodge.clip),
+                distance: dodge.distance,
+                travel: dodge.travel,
+                invulnerable: dodge.invulnerable,
+            }),
+            jump: file.jump.as_ref().map(|jump| Jump {
+                clip: sheets.clip(&jump.clip),
+                speed: jump.speed,
+            }),
+            jump_attack: file
+                .jump
+                .and_then(|jump| jump.attack)
+                .map(|attack| JumpAttack(sheets.attack(&attack))),
+            specials: Specials(
+                file.specials
+                    .iter()
+                    .map(|special| (special.motion, sheets.attack(&special.attack)))
+                    .collect(),
+            ),
+            ai: file.ai.map(|ai| Difficulty {
+                reaction: ai.reaction,
+                aggression: ai.aggression,
+                sight: ai.sight,
+                reach: ai.reach,
+                spacing: ai.spacing,
+            }),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["character.ron"]
+    }
+}
+
+/// Loads the sheets of a character, every clip is a single row of `tile` sized frames
+struct Sheets<'a, 'b> {
+    context: &'a mut LoadContext<'b>,
+    tile: UVec2,
+    /// Layouts by frame count, shared between the clips
+    layouts: HashMap<usize, Handle<TextureAtlasLayout>>,
+}
+
+impl Sheets<'_, '_> {
+    fn clip(&mut self, clip: &ClipFile) -> Clip {
+        let tile = self.tile;
+        let context = &mut *self.context;
+        let layout = self
+            .layouts
+            .entry(clip.frames)
+            .or_insert_with(|| {
+                context.add_labeled_asset(
+                    format!("layout{}", clip.frames),
+                    TextureAtlasLayout::from_grid(tile, clip.frames as u32, 1, None, None),
+                )
+            })
+            .clone();
+        Clip::new(
+            self.context.load(clip.image.clone()),
+            layout,
+            clip.duration,
+            clip.frames,
+        )
+    }
+
+    fn attack(&mut self, attack: &AttackFile) -> AttackStep {
+        AttackStep {
+            clip: self.clip(&attack.clip),
+            cancel: attack.cancel,
+            active: attack.active,
+            hitbox: rect(attack.hitbox),
+            depth: attack.depth,
+            strike: Strike {
+                damage: attack.strike.damage,
+                hitstun: attack.strike.hitstun,
+                knockback: Vec2::new(attack.strike.knockback.0, attack.strike.knockback.1),
+                launch: attack.strike.launch,
+                knockdown: attack.strike.knockdown,
+            },
+        }
+    }
+}
+
+fn rect((x0, y0, x1, y1): (f32, f32, f32, f32)) -> Rect {
+    Rect::new(x0, y0, x1, y1)
+}
+
+// the following types mirror the ones above as they are written in the RON files
+// durations are in milliseconds and boxes are (min x, min y, max x, max y) in sprite space
+
+#[derive(Deserialize)]
+struct CharacterFile {
+    health: f32,
+    speed: SpeedFile,
+    #[serde(default = "default_tile_size")]
+    tile_size: (u32, u32),
+    hurtbox: HurtboxFile,
+    movement: MovementFile,
+    hurt: HurtFile,
+    attacks: ChainFile,
+    #[serde(default)]
+    charge: Option<ChargeFile>,
+    #[serde(default)]
+    run_attack: Option<RunAttackFile>,
+    #[serde(default)]
+    guard_attack: Option<AttackFile>,
+    #[serde(default)]
+    guard: Option<GuardFile>,
+    #[serde(default)]
+    dodge: Option<DodgeFile>,
+    #[serde(default)]
+    jump: Option<JumpFile>,
+    #[serde(default)]
+    specials: Vec<SpecialFile>,
+    #[serde(default)]
+    ai: Option<AiFile>,
+}
+
+impl CharacterFile {
+    /// Rejects values the systems can't play, like empty clips or frame indices past the end of a clip
+    fn validate(&self) -> Result<(), String> {
+        self.movement.idle.validate("movement.idle")?;
+        self.movement.walk.validate("movement.walk")?;
+        self.movement.run.validate("movement.run")?;
+        self.hurt.hurt.validate("hurt.hurt")?;
+        self.hurt.dead.validate("hurt.dead")?;
+        self.hurt.get_up.validate("hurt.get_up")?;
+        for (i, step) in self.attacks.steps.iter().enumerate() {
+            step.validate(&format!("attacks.steps[{i}]"))?;
+        }
+        if let Some(charge) = &self.charge {
+            charge.stance.validate("charge.stance")?;
+            charge.walk.validate("charge.walk")?;
+            charge.release.validate("charge.release")?;
+        }
+        if let Some(run) = &self.run_attack {
+            run.attack.validate("run_attack.attack")?;
+        }
+        if let Some(attack) = &self.guard_attack {
+            attack.validate("guard_attack")?;
+        }
+        if let Some(guard) = &self.guard {
+            guard.clip.validate("guard.clip")?;
+            guard.dizzy_clip.validate("guard.dizzy_clip")?;
+        }
+        if let Some(dodge) = &self.dodge {
+            dodge.validate()?;
+        }
+        if let Some(jump) = &self.jump {
+            jump.clip.validate("jump.clip")?;
+            if let Some(attack) = &jump.attack {
+                attack.validate("jump.attack")?;
+            }
+        }
+        for (i, special) in self.specials.iter().enumerate() {
+            special.attack.validate(&format!("specials[{i}].attack"))?;
+        }
+        Ok(())
+    }
+}
+
+fn default_tile_size() -> (u32, u32) {
+    (128, 128)
+}
+
+#[derive(Deserialize)]
+struct ClipFile {
+    image: String,
+    frames: usize,
+    duration: u64,
+}
+
+impl ClipFile {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        if self.frames == 0 {
+            return Err(format!("{name} has no frames"));
+        }
+        Ok(())
+    }
+
+    /// Checks that the frames `first..=last` are in the clip
+    fn validate_frames(&self, name: &str, (first, last): (usize, usize)) -> Result<(), String> {
+        if first > last || last >= self.frames {
+            return Err(format!(
+                "{name} frames ({first}, {last}) are outside of its {} frames",
+                self.frames
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct SpeedFile {
+    walk: f32,
+    run: f32,
+}
+
+#[derive(Deserialize)]
+struct HurtboxFile {
+    rect: (f32, f32, f32, f32),
+    depth: f32,
+}
+
+#[derive(Deserialize)]
+struct MovementFile {
+    idle: ClipFile,
+    walk: ClipFile,
+    run: ClipFile,
+}
+
+#[derive(Deserialize)]
+struct HurtFile {
+    hurt: ClipFile,
+    dead: ClipFile,
+    get_up: ClipFile,
+}
+
+#[derive(Deserialize)]
+struct StrikeFile {
+    damage: f32,
+    hitstun: u64,
+    #[serde(default)]
+    knockback: (f32, f32),
+    #[serde(default)]
+    launch: f32,
+    #[serde(default)]
+    knockdown: u64,
+}
+
+#[derive(Deserialize)]
+struct AttackFile {
+    clip: ClipFile,
+    cancel: usize,
+    active: (usize, usize),
+    hitbox: (f32, f32, f32, f32),
+    depth: f32,
+    strike: StrikeFile,
+}
+
+impl AttackFile {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        self.clip.validate(name)?;
+        self.clip
+            .validate_frames(&format!("{name} active"), self.active)?;
+        // a cancel frame equal to the frame count never cancels
+        if self.cancel > self.clip.frames {
+            return Err(format!(
+                "{name} cancel frame {} is outside of its {} frames",
+                self.cancel, self.clip.frames
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct ChainFile {
+    steps: Vec<AttackFile>,
+    reset: u64,
+}
+
+#[derive(Deserialize)]
+struct ChargeTierFile {
+    time: u64,
+    scale: f32,
+    color: (f32, f32, f32),
+}
+
+#[derive(Deserialize)]
+struct ChargeFile {
+    stance: ClipFile,
+    walk: ClipFile,
+    release: AttackFile,
+    delay: u64,
+    tiers: Vec<ChargeTierFile>,
+}
+
+#[derive(Deserialize)]
+struct RunAttackFile {
+    attack: AttackFile,
+    slide: f32,
+}
+
+#[derive(Deserialize)]
+struct GuardFile {
+    clip: ClipFile,
+    dizzy_clip: ClipFile,
+    chip: f32,
+    meter: f32,
+    regen: f32,
+    dizzy: u64,
+}
+
+#[derive(Deserialize)]
+struct DodgeFile {
+    clip: ClipFile,
+    distance: f32,
+    travel: usize,
+    invulnerable: (usize, usize),
+}
+
+impl DodgeFile {
+    fn validate(&self) -> Result<(), String> {
+        self.clip.validate("dodge.clip")?;
+        // the roll speed is spread over the time between the first and the travel frame
+        if self.clip.frames < 2 {
+            return Err("dodge.clip needs at least two frames".to_string());
+        }
+        if self.travel == 0 || self.travel >= self.clip.frames {
+            return Err(format!(
+                "dodge travel {} must be between 1 and {}",
+                self.travel,
+                self.clip.frames - 1
+            ));
+        }
+        self.clip
+            .validate_frames("dodge invulnerable", self.invulnerable)
+    }
+}
+
+#[derive(Deserialize)]
+struct JumpFile {
+    clip: ClipFile,
+    speed: f32,
+    #[serde(default)]
+    attack: Option<AttackFile>,
+}
+
+#[derive(Deserialize)]
+struct SpecialFile {
+    motion: Motion,
+    attack: AttackFile,
+}
+
+#[derive(Deserialize)]
+struct AiFile {
+    reaction: u64,
+    aggression: f32,
+    sight: f32,
+    reach: f32,
+    spacing: f32,
+}
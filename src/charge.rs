@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use crate::attack::{AttackState, AttackStep, Attacking};
+use crate::dodge::Dodging;
+use crate::guard::Guarding;
+use crate::jump::Jumping;
+use crate::player::{
+    ActionSet, Character, Controller, Controller1, Controller2, Movement, ReadInputSet, Ready,
+};
+use crate::sprite_sheet::{Clip, NoRepeat};
+use crate::{input, GameState};
+use bevy::prelude::*;
+
+pub struct ChargePlugin;
+
+/// This plugin handles the charge attack, charging starts when the attack is held
+/// The swing the press started is turned into the charge, so charging doesn't throw a light attack first
+/// The character can walk while charging and releasing the attack strikes harder the longer it was held
+impl Plugin for ChargePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (charge_input::<Controller1>, charge_input::<Controller2>)
+                .in_set(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            charge
+                .in_set(ActionSet)
+                .after(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            PostUpdate,
+            (charge_animation, untint).run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Clone)]
+pub struct ChargeTier {
+    /// Charge time needed to reach the tier
+    pub time: Duration,
+    /// Multiplies damage, knockback and launch of the release
+    pub scale: f32,
+    pub color: Color,
+}
+
+#[derive(Component, Clone)]
+pub struct ChargeAttack {
+    pub stance: Clip,
+    pub walk: Clip,
+    pub release: AttackStep,
+    /// How long the attack is held before charging starts
+    pub delay: Duration,
+    /// The first tier is the one the charge starts at
+    pub tiers: Vec<ChargeTier>,
+}
+
+/// Intent to charge, present as long as the charge is held
+#[derive(Component)]
+pub struct WantsCharge;
+
+#[derive(Component)]
+pub struct Charging {
+    time: Duration,
+    tier: usize,
+}
+
+fn charge_input<C: Controller>(
+    mut commands: Commands,
+    players: Query<(Entity, &ChargeAttack, Has<WantsCharge>), (With<C>, With<Character>)>,
+    attack: Query<&input::Held, (With<C>, With<input::Attack>, With<input::Active>)>,
+) {
+    let held = attack.get_single().map(|held| held.0).ok();
+    for (e, charge, wants) in &players {
+        let hold = held.is_some_and(|held| held >= charge.delay);
+        if hold && !wants {
+            commands.entity(e).insert(WantsCharge);
+        } else if !hold && wants {
+            commands.entity(e).remove::<WantsCharge>();
+        }
+    }
+}
+
+fn charge(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut players: Query<
+        (
+            Entity,
+            &ChargeAttack,
+            Option<&mut Charging>,
+            Has<WantsCharge>,
+            Option<&Attacking>,
+            &mut Sprite,
+        ),
+        (Ready, Without<Guarding>, Without<Dodging>, Without<Jumping>),
+    >,
+) {
+    for (e, charge, charging, wants, attacking, mut sprite) in &mut players {
+        // only the opening swing of the chain is made by the press being held
+        if attacking.is_some_and(|attacking| attacking.step != Some(0)) {
+            continue;
+        }
+        match (charging, wants) {
+            (None, true) => {
+                commands
+                    .entity(e)
+                    .remove::<AttackState>()
+                    .remove::<NoRepeat>()
+                    .insert(Charging {
+                        time: Duration::ZERO,
+                        tier: 0,
+                    });
+            }
+            (Some(mut charging), true) => {
+                charging.time += time.delta();
+                while charge
+                    .tiers
+                    .get(charging.tier + 1)
+                    .is_some_and(|next| charging.time >= next.time)
+                {
+                    charging.tier += 1;
+                    sprite.color = charge.tiers[charging.tier].color;
+                }
+            }
+            (Some(charging), false) => {
+                let scale = charge
+                    .tiers
+                    .get(charging.tier)
+                    .map(|tier| tier.scale)
+                    .unwrap_or(1.);
+                let mut release = charge.release.clone();
+                release.strike.damage *= scale;
+                release.strike.knockback *= scale;
+                release.strike.launch *= scale;
+
+                let mut entity = commands.entity(e);
+                entity.remove::<Charging>();
+                Attacking::start(&mut entity, release, None);
+            }
+            (None, false) => {}
+        }
+    }
+}
+
+fn charge_animation(
+    mut commands: Commands,
+    players: Query<
+        (Entity, &ChargeAttack, &Movement),
+        (With<Charging>, Or<(Changed<Movement>, Added<Charging>)>),
+    >,
+) {
+    for (e, charge, &movement) in &players {
+        let clip = if movement == Movement::Idle {
+            &charge.stance
+        } else {
+            &charge.walk
+        };
+        commands.entity(e).insert(clip.bundle());
+    }
+}
+
+fn untint(mut removed: RemovedComponents<Charging>, mut sprites: Query<&mut Sprite>) {
+    for e in removed.read() {
+        if let Ok(mut sprite) = sprites.get_mut(e) {
+            sprite.color = Color::WHITE;
+        }
+    }
+}
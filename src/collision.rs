@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::enemy::Enemy;
+use crate::physics::Height;
+use crate::player::ActionSet;
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct CollisionPlugin;
+
+/// This plugin checks active hitboxes against hurtboxes and sends a [`HitEvent`] for every contact
+/// Boxes are relative to the sprite and facing right, they get mirrored when the sprite is flipped and rise with its height
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HitEvent>()
+            .init_resource::<FriendlyFire>()
+            .add_systems(
+                Update,
+                detect_hits
+                    .in_set(HitSet)
+                    .after(ActionSet)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HitSet;
+
+/// Players can hurt each other when set
+#[derive(Resource, Default)]
+pub struct FriendlyFire(pub bool);
+
+static NEXT_HITBOX_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Area that can be hit
+#[derive(Component, Clone)]
+pub struct Hurtbox {
+    pub rect: Rect,
+    /// Half thickness of the box on the floor (Y) axis
+    pub depth: f32,
+}
+
+/// Hurtboxes of invulnerable characters are skipped
+#[derive(Component)]
+pub struct Invulnerable;
+
+/// Area that hits every hurtbox it touches, once per victim
+#[derive(Component)]
+pub struct Hitbox {
+    pub id: u32,
+    pub rect: Rect,
+    /// Half thickness of the box on the floor (Y) axis
+    pub depth: f32,
+    hit: Vec<Entity>,
+}
+
+impl Hitbox {
+    pub fn new(rect: Rect, depth: f32) -> Hitbox {
+        Hitbox {
+            id: NEXT_HITBOX_ID.fetch_add(1, Ordering::Relaxed),
+            rect,
+            depth,
+            hit: vec![],
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct HitEvent {
+    pub attacker: Entity,
+    pub victim: Entity,
+    pub hitbox_id: u32,
+}
+
+/// The rect in world X and sprite local Y raised by the height, mirrored for flipped sprites
+fn place(rect: Rect, t: &Transform, height: &Height, flip: bool) -> Rect {
+    let (min_x, max_x) = if flip {
+        (-rect.max.x, -rect.min.x)
+    } else {
+        (rect.min.x, rect.max.x)
+    };
+    Rect::new(
+        t.translation.x + min_x,
+        rect.min.y + height.0,
+        t.translation.x + max_x,
+        rect.max.y + height.0,
+    )
+}
+
+fn detect_hits(
+    mut hitboxes: Query<(
+        Entity,
+        &mut Hitbox,
+        &Transform,
+        &Height,
+        &Sprite,
+        Has<Enemy>,
+    )>,
+    hurtboxes: Query<
+        (Entity, &Hurtbox, &Transform, &Height, &Sprite, Has<Enemy>),
+        Without<Invulnerable>,
+    >,
+    friendly_fire: Res<FriendlyFire>,
+    mut hits: EventWriter<HitEvent>,
+) {
+    for (attacker, mut hitbox, at, aheight, asprite, aenemy) in &mut hitboxes {
+        let area = place(hitbox.rect, at, aheight, asprite.flip_x);
+
+        for (victim, hurtbox, vt, vheight, vsprite, venemy) in &hurtboxes {
+            if victim == attacker || hitbox.hit.contains(&victim) {
+                continue;
+            }
+            // enemies don't hurt each other
+            if aenemy && venemy {
+                continue;
+            }
+            if !aenemy && !venemy && !friendly_fire.0 {
+                continue;
+            }
+            if (at.translation.y - vt.translation.y).abs() > hitbox.depth + hurtbox.depth {
+                continue;
+            }
+            if area
+                .intersect(place(hurtbox.rect, vt, vheight, vsprite.flip_x))
+                .is_empty()
+            {
+                continue;
+            }
+
+            hitbox.hit.push(victim);
+            hits.send(HitEvent {
+                attacker,
+                victim,
+                hitbox_id: hitbox.id,
+            });
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+
+use crate::bindings::{Action, Binding, Bindings, PlayerBindings};
+use crate::menu::spawn_button;
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct ControlsPlugin;
+
+/// This plugin is responsible for the controls screen, where the bindings of both players can be changed
+/// Clicking a binding waits for the next key, mouse or pad button, clicking a run setting goes to its next value
+/// The bindings are saved as soon as they change
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(OnEnter(GameState::Controls), setup_controls)
+            .add_systems(
+                Update,
+                (rebind, show_bindings)
+                    .chain()
+                    .run_if(in_state(GameState::Controls)),
+            )
+            .add_systems(OnExit(GameState::Controls), cleanup_controls);
+    }
+}
+
+const TEXT_COLOR: Color = Color::linear_rgb(0.9, 0.9, 0.9);
+
+/// Player and binding waiting for a key or a pad button
+#[derive(Resource, Default)]
+struct Rebinding(Option<(usize, Setting)>);
+
+#[derive(Component)]
+struct Controls;
+/// Width taken by the settings of a player in a row
+const PLAYER_WIDTH: f32 = 400.;
+
+/// Something of a player changed on the controls screen
+#[derive(Clone, Copy, PartialEq)]
+enum Setting {
+    Binding(Action),
+    /// Shares the row of the keyboard binding
+    Pad(Action),
+    Socd,
+    RunMode,
+    DoubleTap,
+}
+
+impl Setting {
+    /// The rows, each starting with its first setting
+    fn rows() -> impl Iterator<Item = Setting> {
+        Action::ALL.into_iter().map(Setting::Binding).chain([
+            Setting::Socd,
+            Setting::RunMode,
+            Setting::DoubleTap,
+        ])
+    }
+
+    fn value(self, bindings: &PlayerBindings) -> String {
+        match self {
+            Setting::Binding(action) => bindings.get(action).to_string(),
+            Setting::Pad(action) => match bindings.pad.get(action) {
+                Some(button) => format!("Pad {button:?}"),
+                None => "Left stick".to_string(),
+            },
+            Setting::Socd => bindings.socd.to_string(),
+            Setting::RunMode => bindings.run_mode.to_string(),
+            Setting::DoubleTap => format!("{} ms", bindings.double_tap),
+        }
+    }
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Setting::Binding(action) => write!(f, "{action}"),
+            Setting::Pad(action) => write!(f, "{action} on pad"),
+            Setting::Socd => write!(f, "Opposite keys"),
+            Setting::RunMode => write!(f, "Run mode"),
+            Setting::DoubleTap => write!(f, "Double tap"),
+        }
+    }
+}
+
+#[derive(Component)]
+struct SettingButton {
+    player: usize,
+    setting: Setting,
+}
+#[derive(Component)]
+struct ResetButton;
+#[derive(Component)]
+struct BackButton;
+/// Tells what is going on, like which key is awaited or which bindings were swapped
+#[derive(Component)]
+struct Message;
+
+fn setup_controls(
+    mut commands: Commands,
+    bindings: Res<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    rebinding.0 = None;
+    let text = |value: String, size: f32| {
+        TextBundle::from_section(
+            value,
+            TextStyle {
+                font_size: size,
+                color: TEXT_COLOR,
+                ..default()
+            },
+        )
+    };
+
+    commands
+        .spawn((
+            Controls,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::linear_rgb(0.05, 0.05, 0.05).into(),
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            for setting in Setting::rows() {
+                children
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(16.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Px(160.),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|label| {
+                            label.spawn(text(setting.to_string(), 32.));
+                        });
+                        for (player, keys) in bindings.players.iter().enumerate() {
+                            let cells = match setting {
+                                Setting::Binding(action) => {
+                                    vec![setting, Setting::Pad(action)]
+                                }
+                                _ => vec![setting],
+                            };
+                            row.spawn(NodeBundle {
+                                style: Style {
+                                    width: Val::Px(PLAYER_WIDTH),
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|player_cells| {
+                                for cell in cells {
+                                    player_cells
+                                        .spawn((
+                                            SettingButton {
+                                                player,
+                                                setting: cell,
+                                            },
+                                            ButtonBundle {
+                                                style: Style {
+                                                    flex_grow: 1.,
+                                                    flex_basis: Val::Px(0.),
+                                                    height: Val::Px(36.),
+                                                    margin: UiRect::all(Val::Px(2.)),
+                                                    justify_content: JustifyContent::Center,
+                                                    align_items: AlignItems::Center,
+                                                    ..default()
+                                                },
+                                                background_color: Color::linear_rgb(
+                                                    0.15, 0.15, 0.15,
+                                                )
+                                                .into(),
+                                                ..default()
+                                            },
+                                        ))
+                                        .with_children(|button| {
+                                            button.spawn(text(cell.value(keys), 28.));
+                                        });
+                                }
+                            });
+                        }
+                    });
+            }
+            children.spawn((Message, text(String::new(), 28.)));
+            spawn_button(children, "Reset to defaults", ResetButton);
+            spawn_button(children, "Back", BackButton);
+        });
+}
+
+/// Clicks pick the binding to change, then the next key or mouse button pressed is bound
+fn rebind(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+    (keys, mouse, pads): (
+        Res<ButtonInput<KeyCode>>,
+        Res<ButtonInput<MouseButton>>,
+        Res<ButtonInput<GamepadButton>>,
+    ),
+    clicked: Query<
+        (
+            &Interaction,
+            Option<&SettingButton>,
+            Has<ResetButton>,
+            Has<BackButton>,
+        ),
+        Changed<Interaction>,
+    >,
+    mut message: Query<&mut Text, With<Message>>,
+) {
+    let mut say = |value: String| {
+        for mut text in &mut message {
+            text.sections[0].value.clone_from(&value);
+        }
+    };
+
+    if let Some((player, setting)) = rebinding.0 {
+        if keys.just_pressed(KeyCode::Escape) {
+            rebinding.0 = None;
+            say(String::new());
+            return;
+        }
+        let action = match setting {
+            Setting::Binding(action) => action,
+            Setting::Pad(action) => {
+                let Some(button) = pads.get_just_pressed().next().map(|b| b.button_type) else {
+                    return;
+                };
+                rebinding.0 = None;
+                let swapped = bindings.players[player].pad.rebind(action, button);
+                say(match swapped {
+                    Some(other) => format!(
+                        "Pad {button:?} was used by P{} {other}, it got the replaced button",
+                        player + 1
+                    ),
+                    None => String::new(),
+                });
+                bindings.save();
+                return;
+            }
+            _ => {
+                rebinding.0 = None;
+                return;
+            }
+        };
+        let pressed = keys
+            .get_just_pressed()
+            .next()
+            .map(|&key| Binding::Key(key))
+            .or_else(|| mouse.get_just_pressed().next().map(|&b| Binding::Mouse(b)));
+        let Some(binding) = pressed else {
+            return;
+        };
+        rebinding.0 = None;
+
+        let conflict = bindings
+            .conflict(binding)
+            .filter(|&conflict| conflict != (player, action));
+        if !bindings.rebind(player, action, binding) {
+            say(format!("{binding} can't be used for {action}"));
+            return;
+        }
+        say(match conflict {
+            Some((other, other_action)) => format!(
+                "{binding} was used by P{} {other_action}, it got the replaced binding",
+                other + 1
+            ),
+            None => String::new(),
+        });
+        bindings.save();
+        return;
+    }
+
+    for (&interaction, setting, reset, back) in &clicked {
+        if interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(&SettingButton { player, setting }) = setting {
+            let keys = &mut bindings.players[player];
+            match setting {
+                Setting::Binding(action) => {
+                    rebinding.0 = Some((player, setting));
+                    say(format!(
+                        "Press a key for P{} {action}, Escape to cancel",
+                        player + 1
+                    ));
+                }
+                Setting::Pad(action) if action.takes_pad() => {
+                    rebinding.0 = Some((player, setting));
+                    say(format!(
+                        "Press a pad button for P{} {action}, Escape to cancel",
+                        player + 1
+                    ));
+                }
+                Setting::Pad(_) => {}
+                Setting::Socd => {
+                    keys.socd = keys.socd.next();
+                    bindings.save();
+                }
+                Setting::RunMode => {
+                    keys.run_mode = keys.run_mode.next();
+                    bindings.save();
+                }
+                Setting::DoubleTap => {
+                    keys.double_tap = keys.next_double_tap();
+                    bindings.save();
+                }
+            }
+        } else if reset {
+            *bindings = Bindings::default();
+            bindings.save();
+            say("Bindings reset to defaults".to_string());
+        } else if back {
+            next_state.set(GameState::Menu);
+        }
+    }
+}
+
+fn show_bindings(
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    buttons: Query<(&SettingButton, &Children)>,
+    mut texts: Query<&mut Text, Without<Message>>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (button, children) in &buttons {
+        let label = if rebinding.0 == Some((button.player, button.setting)) {
+            "...".to_string()
+        } else {
+            button.setting.value(&bindings.players[button.player])
+        };
+        for &child in children {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].value.clone_from(&label);
+            }
+        }
+    }
+}
+
+fn cleanup_controls(mut commands: Commands, controls: Query<Entity, With<Controls>>) {
+    for entity in &controls {
+        commands.entity(entity).despawn_recursive();
+    }
+}
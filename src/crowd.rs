@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::attack::Attacking;
+use crate::health::CharacterDied;
+use crate::player::ReadInputSet;
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct CrowdPlugin;
+
+/// This plugin keeps a mob from hitting the same target all at once
+/// Enemies need one of the few attack tokens of a target to go at it, the others wait around it
+impl Plugin for CrowdPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AttackSlots>().add_systems(
+            Update,
+            (forget, rotate)
+                .chain()
+                .in_set(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Depth between two waiting lanes
+const LANE_GAP: f32 = 16.;
+
+#[derive(Resource)]
+pub struct AttackSlots {
+    /// Enemies allowed to go at a target at once
+    pub tokens: usize,
+    /// How long a token is kept before it goes to the next enemy in line
+    pub hold: Duration,
+    targets: HashMap<Entity, Crowd>,
+}
+
+impl Default for AttackSlots {
+    fn default() -> Self {
+        AttackSlots {
+            tokens: 2,
+            hold: Duration::from_millis(3000),
+            targets: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Crowd {
+    /// Token holders with how long they held it
+    holders: Vec<(Entity, Duration)>,
+    /// Enemies waiting for a token, first in line first
+    waiting: Vec<Entity>,
+}
+
+impl AttackSlots {
+    /// Asks for a token on the target, true when the enemy holds one
+    /// An enemy goes after one target at a time, it leaves the lines of the others
+    /// One that stops asking should [`AttackSlots::release`] so it doesn't hold up the line
+    pub fn request(&mut self, target: Entity, enemy: Entity) -> bool {
+        for (_, crowd) in self.targets.iter_mut().filter(|(&t, _)| t != target) {
+            crowd.holders.retain(|&(e, _)| e != enemy);
+            crowd.waiting.retain(|&e| e != enemy);
+        }
+        let crowd = self.targets.entry(target).or_default();
+        if crowd.holders.iter().any(|&(e, _)| e == enemy) {
+            return true;
+        }
+        if !crowd.waiting.contains(&enemy) {
+            crowd.waiting.push(enemy);
+        }
+        if crowd.holders.len() < self.tokens && crowd.waiting.first() == Some(&enemy) {
+            crowd.waiting.remove(0);
+            crowd.holders.push((enemy, Duration::ZERO));
+            return true;
+        }
+        false
+    }
+
+    /// Gives back the token of the enemy and leaves every line it is in
+    pub fn release(&mut self, enemy: Entity) {
+        for crowd in self.targets.values_mut() {
+            crowd.holders.retain(|&(e, _)| e != enemy);
+            crowd.waiting.retain(|&e| e != enemy);
+        }
+    }
+
+    /// Where an enemy without a token waits, out of reach on its side of the target
+    /// The ones in line share the depth band so they don't stack on each other
+    pub fn post(
+        &self,
+        target: Entity,
+        enemy: Entity,
+        here: Vec2,
+        there: Vec2,
+        distance: f32,
+    ) -> Vec2 {
+        let place = self
+            .targets
+            .get(&target)
+            .and_then(|crowd| crowd.waiting.iter().position(|&e| e == enemy))
+            .unwrap_or_default();
+        let side = if here.x < there.x { -1. } else { 1. };
+        // lanes go 0, 1, -1, 2, -2...
+        let lane = place.div_ceil(2) as f32 * if place.is_multiple_of(2) { -1. } else { 1. };
+        Vec2::new(there.x + side * distance, there.y + lane * LANE_GAP)
+    }
+}
+
+/// Tokens held for too long go to the back of the line, once the attack in progress is over
+fn rotate(time: Res<Time>, mut slots: ResMut<AttackSlots>, attacking: Query<(), With<Attacking>>) {
+    let hold = slots.hold;
+    for crowd in slots.targets.values_mut() {
+        let mut done = vec![];
+        for (e, held) in &mut crowd.holders {
+            *held += time.delta();
+            if *held >= hold && !attacking.contains(*e) {
+                done.push(*e);
+            }
+        }
+        crowd.holders.retain(|(e, _)| !done.contains(e));
+        crowd.waiting.extend(done);
+    }
+}
+
+/// The dead neither attack nor get attacked
+fn forget(mut slots: ResMut<AttackSlots>, mut died: EventReader<CharacterDied>) {
+    for &CharacterDied(e) in died.read() {
+        slots.targets.remove(&e);
+        slots.release(e);
+    }
+}
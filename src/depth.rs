@@ -0,0 +1,64 @@
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct DepthPlugin;
+
+/// This plugin draws what stands lower on the floor in front of what stands higher
+/// The depth only depends on the floor position, a character in the air is drawn where its shadow is
+impl Plugin for DepthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            sort.before(TransformSystem::TransformPropagate)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Z of things standing at floor Y 0, above the background layers
+const SORT_Z: f32 = 10.;
+/// Z step for one pixel of floor Y
+const DEPTH_PER_PIXEL: f32 = 0.01;
+/// Least Z step between two things, far above the float precision around [`SORT_Z`]
+/// Things closer than that on the floor are spread apart, so a whole crowd stays within a few pixels
+const TIE_BREAK: f32 = 0.000_1;
+/// Z of what is drawn above everything standing on the floor
+const ON_TOP_Z: f32 = 50.;
+
+/// Sorted by its floor Y
+#[derive(Component, Default)]
+pub struct YSort {
+    /// Offset from the position to where it touches the floor
+    pub feet: f32,
+}
+
+/// Drawn above everything standing on the floor, for effects
+#[derive(Component)]
+pub struct OnTop;
+
+fn sort(mut sorted: Query<(Entity, &mut Transform, &YSort, Has<OnTop>)>) {
+    let mut order: Vec<_> = sorted
+        .iter()
+        .filter(|&(.., on_top)| !on_top)
+        .map(|(e, t, sort, _)| (SORT_Z - (t.translation.y + sort.feet) * DEPTH_PER_PIXEL, e))
+        .collect();
+    // the same things always win ties, so overlapping sprites don't flicker
+    order.sort_by(|(a, ae), (b, be)| a.total_cmp(b).then(ae.cmp(be)));
+
+    let mut last = f32::NEG_INFINITY;
+    for (z, e) in order {
+        let z = z.max(last + TIE_BREAK);
+        last = z;
+        if let Ok((_, mut t, ..)) = sorted.get_mut(e) {
+            if t.translation.z != z {
+                t.translation.z = z;
+            }
+        }
+    }
+
+    for (_, mut t, _, on_top) in &mut sorted {
+        if on_top && t.translation.z != ON_TOP_Z {
+            t.translation.z = ON_TOP_Z;
+        }
+    }
+}
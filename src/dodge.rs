@@ -0,0 +1,173 @@
+use crate::attack::{AttackState, Attacking};
+use crate::charge::Charging;
+use crate::collision::Invulnerable;
+use crate::guard::Guarding;
+use crate::jump::Jumping;
+use crate::player::{
+    ActionSet, Character, Controller, Controller1, Controller2, Direction, LevelLimit, Movement,
+    ReadInputSet, Ready,
+};
+use crate::sprite_sheet::{AnimationEnded, Clip, NoRepeat};
+use crate::{input, GameState};
+use bevy::prelude::*;
+
+pub struct DodgePlugin;
+
+/// This plugin handles the dodge roll
+/// The roll travels a fixed distance over its first frames and can't be hit during its i-frames
+/// It can cancel a swing that reached its cancel frame
+impl Plugin for DodgePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (dodge_input::<Controller1>, dodge_input::<Controller2>)
+                .in_set(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (dodge, roll.run_if(resource_exists::<LevelLimit>), end_dodge)
+                .chain()
+                .in_set(ActionSet)
+                .after(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct DodgeRoll {
+    pub clip: Clip,
+    pub distance: f32,
+    /// Frames spent travelling, the ones after are recovery
+    pub travel: usize,
+    /// First and last frame that can't be hit
+    pub invulnerable: (usize, usize),
+}
+
+/// Intent to dodge, set by a controller and consumed by [`dodge`]
+#[derive(Component)]
+pub struct WantsDodge;
+
+#[derive(Component)]
+pub struct Dodging {
+    velocity: Vec2,
+}
+
+/// Everything a roll puts on its character, removed when the roll is interrupted
+pub type DodgeState = (Dodging, Invulnerable);
+
+/// Asks to dodge as long as a buffered press wasn't used by a roll
+fn dodge_input<C: Controller>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut buffer: ResMut<input::InputBuffer<C>>,
+    players: Query<(Entity, Option<Ref<Dodging>>), (With<C>, With<Character>)>,
+) {
+    for (e, dodging) in &players {
+        if dodging.is_some_and(|dodging| dodging.is_added()) {
+            buffer.consume(input::Press::Dodge, time.elapsed());
+        }
+        if buffer.buffered(input::Press::Dodge, time.elapsed()) {
+            commands.entity(e).insert(WantsDodge);
+        }
+    }
+}
+
+fn dodge(
+    mut commands: Commands,
+    players: Query<
+        (Entity, Option<(&DodgeRoll, &Movement, &Direction, &Sprite)>),
+        With<WantsDodge>,
+    >,
+    free: Query<
+        Option<(&Attacking, &TextureAtlas)>,
+        (
+            Ready,
+            Without<Guarding>,
+            Without<Dodging>,
+            Without<Charging>,
+            Without<Jumping>,
+        ),
+    >,
+) {
+    for (e, roll) in &players {
+        commands.entity(e).remove::<WantsDodge>();
+
+        let Some((roll, &movement, &Direction(x, y), sprite)) = roll else {
+            continue;
+        };
+        let Ok(attacking) = free.get(e) else {
+            continue;
+        };
+        // a swing past its cancel frame can be cancelled into a roll
+        if attacking.is_some_and(|(attacking, atlas)| atlas.index < attacking.attack.cancel) {
+            continue;
+        }
+
+        let way = if movement == Movement::Idle || (x == 0. && y == 0.) {
+            Vec2::new(if sprite.flip_x { -1. } else { 1. }, 0.)
+        } else {
+            Vec2::new(x, y * 0.6).normalize()
+        };
+        let frame = roll.clip.duration as f32 / 1000. / (roll.clip.frames - 1) as f32;
+        let speed = roll.distance / (roll.travel as f32 * frame);
+
+        commands
+            .entity(e)
+            .remove::<AttackState>()
+            .insert((
+                Dodging {
+                    velocity: way * speed,
+                },
+                NoRepeat,
+            ))
+            .insert(roll.clip.bundle());
+    }
+}
+
+fn roll(
+    mut commands: Commands,
+    time: Res<Time>,
+    limit: Res<LevelLimit>,
+    mut players: Query<(
+        Entity,
+        &mut Transform,
+        &Dodging,
+        &DodgeRoll,
+        &TextureAtlas,
+        Has<Invulnerable>,
+    )>,
+) {
+    let LevelLimit(bounds) = *limit;
+    for (e, mut t, dodging, roll, atlas, invulnerable) in &mut players {
+        if atlas.index < roll.travel {
+            let step = dodging.velocity * time.delta_seconds();
+            t.translation.x = (t.translation.x + step.x).clamp(bounds.min.x, bounds.max.x);
+            t.translation.y = (t.translation.y + step.y).clamp(bounds.min.y, bounds.max.y);
+        }
+
+        let (first, last) = roll.invulnerable;
+        let frames = first..=last;
+        if frames.contains(&atlas.index) && !invulnerable {
+            commands.entity(e).insert(Invulnerable);
+        } else if !frames.contains(&atlas.index) && invulnerable {
+            commands.entity(e).remove::<Invulnerable>();
+        }
+    }
+}
+
+fn end_dodge(
+    mut commands: Commands,
+    mut ended: EventReader<AnimationEnded>,
+    dodging: Query<(), With<Dodging>>,
+) {
+    for &AnimationEnded(e) in ended.read() {
+        if dodging.contains(e) {
+            commands
+                .entity(e)
+                .remove::<(Dodging, NoRepeat, Invulnerable)>()
+                .insert(Movement::Idle);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::enemy::Enemy;
+use crate::health::CharacterDied;
+use crate::level::{Entry, Level};
+use crate::physics::{Height, VerticalVelocity};
+use crate::player::{Alive, Character, Direction, Movement, ReadInputSet};
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct EncounterPlugin;
+
+/// This plugin runs the encounters of the level, reaching a trigger locks the screen and spawns waves of enemies
+/// The next wave comes once the previous one is defeated, after the last one the lock is released
+impl Plugin for EncounterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Encounters>()
+            .init_resource::<ScreenLock>()
+            .add_systems(OnEnter(GameState::Playing), reset)
+            .add_systems(
+                Update,
+                (trigger, waves, enter, go_prompt)
+                    .chain()
+                    .in_set(ReadInputSet)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// How far past the lock walking enemies start
+const OFF_SCREEN: f32 = 48.;
+/// Height dropping enemies fall from
+const DROP_HEIGHT: f32 = 200.;
+const ARRIVE: f32 = 4.;
+/// Seconds the GO prompt blinks for
+const GO_TIME: f32 = 3.;
+const GO_BLINK: f32 = 0.25;
+
+/// Progress through the encounters of the level
+#[derive(Resource, Default)]
+pub struct Encounters {
+    /// Index of the next encounter to trigger
+    next: usize,
+    /// Encounter in progress and its current wave
+    active: Option<(usize, usize)>,
+}
+
+/// Floor X range the players are held in, set during an encounter
+#[derive(Resource, Default)]
+pub struct ScreenLock(pub Option<(f32, f32)>);
+
+/// An enemy making its way into the locked screen, it doesn't fight before it's done
+#[derive(Component)]
+pub enum Entrance {
+    Walk { to: f32 },
+    Drop,
+    Falling,
+}
+
+/// Enemy of the wave in progress
+#[derive(Component)]
+struct WaveMember;
+
+#[derive(Component)]
+struct GoPrompt(Timer);
+
+fn reset(mut encounters: ResMut<Encounters>, mut lock: ResMut<ScreenLock>) {
+    *encounters = Encounters::default();
+    lock.0 = None;
+}
+
+fn trigger(
+    mut encounters: ResMut<Encounters>,
+    mut lock: ResMut<ScreenLock>,
+    level: Level,
+    players: Query<&Transform, (With<Character>, With<Alive>, Without<Enemy>)>,
+) {
+    if encounters.active.is_some() {
+        return;
+    }
+    let Some(encounter) = level
+        .def()
+        .and_then(|level| level.encounters.get(encounters.next))
+    else {
+        return;
+    };
+    if players.iter().any(|t| t.translation.x >= encounter.trigger) {
+        lock.0 = Some(encounter.lock);
+        encounters.active = Some((encounters.next, 0));
+        encounters.next += 1;
+    }
+}
+
+fn waves(
+    mut commands: Commands,
+    mut encounters: ResMut<Encounters>,
+    mut lock: ResMut<ScreenLock>,
+    level: Level,
+    mut died: EventReader<CharacterDied>,
+    members: Query<(), With<WaveMember>>,
+) {
+    let mut defeated = 0;
+    for &CharacterDied(e) in died.read() {
+        if members.contains(e) {
+            commands.entity(e).remove::<WaveMember>();
+            defeated += 1;
+        }
+    }
+    let Some((index, step)) = encounters.active else {
+        return;
+    };
+    if members.iter().len() > defeated {
+        return;
+    }
+
+    let Some(def) = level.def() else {
+        return;
+    };
+    let Some(encounter) = def.encounters.get(index) else {
+        return;
+    };
+    let Some(wave) = encounter.waves.get(step) else {
+        encounters.active = None;
+        lock.0 = None;
+        spawn_go_prompt(&mut commands);
+        return;
+    };
+
+    let (left, right) = encounter.lock;
+    let floor = def.floor;
+    for spawn in &wave.enemies {
+        // walking in starts as far out as the floor goes
+        let at = spawn.at.clamp(floor.min, floor.max);
+        let (x, entrance) = match spawn.entry {
+            Entry::Left => (
+                (left - OFF_SCREEN).max(floor.min.x),
+                Entrance::Walk { to: at.x },
+            ),
+            Entry::Right => (
+                (right + OFF_SCREEN).min(floor.max.x),
+                Entrance::Walk { to: at.x },
+            ),
+            Entry::Drop => (at.x, Entrance::Drop),
+        };
+        commands.spawn((
+            Name::new("Enemy"),
+            Character,
+            Enemy,
+            WaveMember,
+            entrance,
+            spawn.character.clone(),
+            Transform::from_xyz(x, at.y, 10.),
+        ));
+    }
+    encounters.active = Some((index, step + 1));
+}
+
+fn enter(
+    mut commands: Commands,
+    mut enemies: Query<
+        (
+            Entity,
+            &mut Entrance,
+            &Transform,
+            &mut Direction,
+            &mut Movement,
+            &mut Height,
+            Has<VerticalVelocity>,
+        ),
+        With<Alive>,
+    >,
+) {
+    for (e, mut entrance, t, mut direction, mut movement, mut height, airborne) in &mut enemies {
+        match *entrance {
+            Entrance::Walk { to } => {
+                let gap = to - t.translation.x;
+                if gap.abs() <= ARRIVE {
+                    movement.set_if_neq(Movement::Idle);
+                    commands.entity(e).remove::<Entrance>();
+                } else {
+                    direction.set_if_neq(Direction(gap.signum(), 0.));
+                    movement.set_if_neq(Movement::Walk);
+                }
+            }
+            Entrance::Drop => {
+                height.0 = DROP_HEIGHT;
+                commands.entity(e).insert(VerticalVelocity(0.));
+                *entrance = Entrance::Falling;
+            }
+            Entrance::Falling if !airborne => {
+                commands.entity(e).remove::<Entrance>();
+            }
+            Entrance::Falling => {}
+        }
+    }
+}
+
+fn spawn_go_prompt(commands: &mut Commands) {
+    commands
+        .spawn((
+            GoPrompt(Timer::from_seconds(GO_TIME, TimerMode::Once)),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(40.),
+                    top: Val::Percent(40.),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "GO \u{2192}",
+                TextStyle {
+                    font_size: 60.0,
+                    color: Color::linear_rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn go_prompt(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut prompts: Query<(Entity, &mut GoPrompt, &mut Visibility)>,
+) {
+    for (e, mut prompt, mut visibility) in &mut prompts {
+        if prompt.0.tick(time.delta()).finished() {
+            commands.entity(e).despawn_recursive();
+            continue;
+        }
+        let blink = (prompt.0.elapsed_secs() / GO_BLINK) as u32;
+        *visibility = if blink.is_multiple_of(2) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
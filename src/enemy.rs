@@ -0,0 +1,321 @@
+use std::time::Duration;
+
+use crate::attack::{Attacking, WantsAttack};
+use crate::character::CharacterDef;
+use crate::charge::Charging;
+use crate::crowd::AttackSlots;
+use crate::dodge::Dodging;
+use crate::encounter::Entrance;
+use crate::guard::{Dizzy, GuardStun};
+use crate::health::{Dead, Hurt};
+use crate::physics::KnockedDown;
+use crate::player::{Alive, Character, Direction, Free, Movement, ReadInputSet, Ready};
+use crate::sprite_sheet::AnimationEnded;
+use crate::GameState;
+use bevy::prelude::*;
+use rand::Rng;
+
+pub struct EnemyPlugin;
+
+/// This plugin drives the enemies with a small behaviour state machine
+/// Enemies set the same intents as the player input so both go through the same character systems
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (think, steer, press)
+                .chain()
+                .in_set(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(Update, fade_out.run_if(in_state(GameState::Playing)))
+        .add_systems(PostUpdate, init_brain.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Depth difference under which a target counts as being on the same lane
+const LANE: f32 = 8.;
+/// Distance under which a goal counts as reached
+const ARRIVE: f32 = 4.;
+/// Seconds a dead enemy takes to fade out once its death animation is over
+const FADE_OUT: f32 = 1.;
+
+#[derive(Component)]
+pub struct Enemy;
+
+/// How an enemy fights, loaded with its [`CharacterDef`]
+#[derive(Component, Clone)]
+pub struct Difficulty {
+    /// Milliseconds between two decisions, and before reacting again after being hit
+    pub reaction: u64,
+    /// Chance to pick the aggressive option of a decision, from 0 to 1
+    pub aggression: f32,
+    /// Distance a target is noticed from
+    pub sight: f32,
+    /// Horizontal distance an attack is thrown from
+    pub reach: f32,
+    /// Distance kept to the target while flanking
+    pub spacing: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Behaviour {
+    Idle,
+    Patrol,
+    Approach,
+    Flank,
+    Attack,
+    Retreat,
+    Stagger,
+}
+
+#[derive(Component)]
+pub struct Brain {
+    pub behaviour: Behaviour,
+    pub target: Option<Entity>,
+    /// Time until the next decision
+    timer: Timer,
+    /// Where the enemy was spawned, it patrols around it
+    home: Vec2,
+    /// Point walked to while patrolling or flanking
+    goal: Vec2,
+    /// Swings planned and swings started in the current attack
+    swings: (usize, usize),
+}
+
+/// A dead enemy fading out, it is despawned once the timer ends
+#[derive(Component)]
+struct Corpse(Timer);
+
+fn fade_out(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ended: EventReader<AnimationEnded>,
+    dead: Query<(), (With<Enemy>, With<Dead>, Without<Corpse>)>,
+    mut corpses: Query<(Entity, &mut Corpse, &mut Sprite)>,
+) {
+    for &AnimationEnded(e) in ended.read() {
+        if dead.contains(e) {
+            // the shadow goes at once
+            commands
+                .entity(e)
+                .despawn_descendants()
+                .insert(Corpse(Timer::from_seconds(FADE_OUT, TimerMode::Once)));
+        }
+    }
+
+    for (e, mut corpse, mut sprite) in &mut corpses {
+        if corpse.0.tick(time.delta()).finished() {
+            commands.entity(e).despawn_recursive();
+            continue;
+        }
+        sprite.color.set_alpha(corpse.0.fraction_remaining());
+    }
+}
+
+fn init_brain(
+    mut commands: Commands,
+    enemies: Query<(Entity, &Handle<CharacterDef>, &Transform), Added<Enemy>>,
+    defs: Res<Assets<CharacterDef>>,
+) {
+    for (e, handle, t) in &enemies {
+        let Some(difficulty) = defs.get(handle).and_then(|def| def.ai.clone()) else {
+            continue;
+        };
+        let home = t.translation.truncate();
+        commands.entity(e).insert((
+            Brain {
+                behaviour: Behaviour::Idle,
+                target: None,
+                timer: Timer::new(Duration::from_millis(difficulty.reaction), TimerMode::Once),
+                home,
+                goal: home,
+                swings: (0, 0),
+            },
+            difficulty,
+        ));
+    }
+}
+
+fn think(
+    time: Res<Time>,
+    mut slots: ResMut<AttackSlots>,
+    mut enemies: Query<
+        (
+            Entity,
+            &mut Brain,
+            &Difficulty,
+            &Transform,
+            Has<Attacking>,
+            Has<Hurt>,
+            Has<KnockedDown>,
+            Has<Dizzy>,
+            Has<GuardStun>,
+        ),
+        (With<Enemy>, With<Alive>, Without<Entrance>),
+    >,
+    targets: Query<(Entity, &Transform, &Sprite), (With<Character>, With<Alive>, Without<Enemy>)>,
+) {
+    let mut rng = rand::thread_rng();
+    for (e, mut brain, difficulty, t, attacking, hurt, down, dizzy, stunned) in &mut enemies {
+        let reaction = Duration::from_millis(difficulty.reaction);
+        if hurt || down || dizzy || stunned {
+            if brain.behaviour != Behaviour::Stagger {
+                slots.release(e);
+            }
+            brain.behaviour = Behaviour::Stagger;
+            brain.swings = (0, 0);
+            continue;
+        }
+        if brain.behaviour == Behaviour::Stagger {
+            brain.timer = Timer::new(reaction, TimerMode::Once);
+            brain.behaviour = if rng.gen::<f32>() < difficulty.aggression {
+                Behaviour::Approach
+            } else {
+                Behaviour::Retreat
+            };
+            continue;
+        }
+        if brain.behaviour == Behaviour::Attack {
+            if attacking || brain.swings.1 == 0 {
+                continue;
+            }
+            // let the next one in line have a go
+            slots.release(e);
+            brain.swings = (0, 0);
+        }
+        if !brain.timer.tick(time.delta()).finished() {
+            continue;
+        }
+        brain.timer = Timer::new(reaction.mul_f32(rng.gen_range(0.75..1.25)), TimerMode::Once);
+
+        let here = t.translation.truncate();
+        let target = targets
+            .iter()
+            .map(|(e, t, sprite)| (e, t.translation.truncate(), sprite.flip_x))
+            .filter(|(_, there, _)| there.distance(here) <= difficulty.sight)
+            .min_by(|(_, a, _), (_, b, _)| a.distance(here).total_cmp(&b.distance(here)));
+        brain.target = target.map(|(e, _, _)| e);
+
+        let aggressive = rng.gen::<f32>() < difficulty.aggression;
+        let token = match target {
+            Some((target, _, _)) if aggressive => slots.request(target, e),
+            _ => {
+                slots.release(e);
+                false
+            }
+        };
+        brain.behaviour = match target {
+            None if rng.gen_bool(0.5) => {
+                brain.goal =
+                    brain.home + Vec2::new(rng.gen_range(-60. ..60.), rng.gen_range(-20. ..20.));
+                Behaviour::Patrol
+            }
+            None => Behaviour::Idle,
+            Some((target, there, _)) if aggressive && !token => {
+                // wait for a token out of reach
+                brain.goal = slots.post(target, e, here, there, difficulty.spacing * 1.5);
+                Behaviour::Flank
+            }
+            Some((_, there, _))
+                if (there.x - here.x).abs() <= difficulty.reach
+                    && (there.y - here.y).abs() <= LANE =>
+            {
+                if aggressive {
+                    brain.swings = (rng.gen_range(1..=3), 0);
+                    Behaviour::Attack
+                } else {
+                    Behaviour::Retreat
+                }
+            }
+            Some(_) if aggressive => Behaviour::Approach,
+            Some((_, there, flip)) => {
+                // get behind the target, on a lane next to it
+                let behind = if flip { 1. } else { -1. };
+                let side = if rng.gen_bool(0.5) { 1. } else { -1. };
+                brain.goal = there + Vec2::new(behind * difficulty.spacing, side * LANE * 2.);
+                Behaviour::Flank
+            }
+        };
+    }
+}
+
+fn steer(
+    mut enemies: Query<
+        (
+            &Brain,
+            &Difficulty,
+            &Transform,
+            &mut Direction,
+            &mut Movement,
+        ),
+        (With<Enemy>, Free, Without<Charging>, Without<Entrance>),
+    >,
+    targets: Query<&Transform, (With<Alive>, Without<Enemy>)>,
+) {
+    for (brain, difficulty, t, mut direction, mut movement) in &mut enemies {
+        let here = t.translation.truncate();
+        let target = brain
+            .target
+            .and_then(|e| targets.get(e).ok())
+            .map(|t| t.translation.truncate());
+
+        let goal = match (brain.behaviour, target) {
+            (Behaviour::Patrol | Behaviour::Flank, _) => Some((brain.goal, Movement::Walk)),
+            (Behaviour::Approach, Some(there)) => {
+                let side = if here.x < there.x { -1. } else { 1. };
+                let goal = Vec2::new(there.x + side * difficulty.reach * 0.8, there.y);
+                let pace = if goal.distance(here) > difficulty.reach * 3. {
+                    Movement::Run
+                } else {
+                    Movement::Walk
+                };
+                Some((goal, pace))
+            }
+            (Behaviour::Retreat, Some(there)) => {
+                let away = if here.x < there.x { -1. } else { 1. };
+                Some((
+                    here + Vec2::new(away * difficulty.spacing, 0.),
+                    Movement::Walk,
+                ))
+            }
+            _ => None,
+        };
+
+        match goal {
+            Some((goal, pace)) if goal.distance(here) > ARRIVE => {
+                let way = (goal - here).normalize();
+                direction.set_if_neq(Direction(way.x, way.y));
+                movement.set_if_neq(pace);
+            }
+            _ => {
+                movement.set_if_neq(Movement::Idle);
+                // keep facing the target, retreats back off while still looking at it
+                if let Some(there) = target.filter(|there| (there.x - here.x).abs() > 1.) {
+                    direction.set_if_neq(Direction((there.x - here.x).signum(), 0.));
+                }
+            }
+        }
+    }
+}
+
+fn press(
+    mut commands: Commands,
+    mut enemies: Query<
+        (Entity, &mut Brain, Option<Ref<Attacking>>),
+        (With<Enemy>, Ready, Without<Dodging>, Without<Entrance>),
+    >,
+) {
+    for (e, mut brain, attacking) in &mut enemies {
+        if brain.behaviour != Behaviour::Attack {
+            continue;
+        }
+        if attacking.is_some_and(|attacking| attacking.is_changed()) {
+            brain.swings.1 += 1;
+        }
+        let (planned, started) = brain.swings;
+        if started < planned {
+            commands.entity(e).insert(WantsAttack);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use crate::attack::{Attacking, Strike};
+use crate::charge::Charging;
+use crate::collision::{HitEvent, HitSet, Hitbox};
+use crate::dodge::Dodging;
+use crate::health::Health;
+use crate::jump::Jumping;
+use crate::physics::Knockback;
+use crate::player::{
+    ActionSet, Character, Controller, Controller1, Controller2, Movement, ReadInputSet, Ready,
+};
+use crate::sprite_sheet::{Clip, NoRepeat};
+use crate::{input, GameState};
+use bevy::prelude::*;
+
+pub struct GuardPlugin;
+
+/// This plugin handles guarding, frontal hits on a guarding character only chip its health
+/// Every blocked hit drains the guard meter, the guard breaks into a dizzy state when it is empty
+impl Plugin for GuardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (guard_input::<Controller1>, guard_input::<Controller2>)
+                .in_set(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (guard, guard_stun, regen, dizzy)
+                .in_set(ActionSet)
+                .after(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            block.after(HitSet).run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct Guard {
+    pub clip: Clip,
+    pub dizzy_clip: Clip,
+    /// Share of the damage that goes through the guard
+    pub chip: f32,
+    pub meter: f32,
+    /// Meter refilled per second while not guarding
+    pub regen: f32,
+    pub dizzy: Duration,
+}
+
+#[derive(Component)]
+pub struct GuardMeter(pub f32);
+
+/// Intent to guard, present as long as the guard is held
+#[derive(Component)]
+pub struct WantsGuard;
+
+#[derive(Component)]
+pub struct Guarding;
+
+/// Recoil of a blocked hit, the guard can't be dropped until it ends
+#[derive(Component)]
+pub struct GuardStun(Timer);
+
+#[derive(Component)]
+pub struct Dizzy(Timer);
+
+/// Everything a guard puts on its character, removed when the guard is broken through
+pub type GuardState = (Guarding, GuardStun);
+
+/// Whether a character looking the way its sprite does faces a point
+pub fn faces(t: &Transform, sprite: &Sprite, x: f32) -> bool {
+    let facing = if sprite.flip_x { -1. } else { 1. };
+    (x - t.translation.x) * facing >= 0.
+}
+
+fn guard_input<C: Controller>(
+    mut commands: Commands,
+    players: Query<(Entity, Has<WantsGuard>), (With<C>, With<Character>)>,
+    held: Query<(), (With<C>, With<input::Guard>, With<input::Active>)>,
+) {
+    let held = !held.is_empty();
+    for (e, wants) in &players {
+        if held && !wants {
+            commands.entity(e).insert(WantsGuard);
+        } else if !held && wants {
+            commands.entity(e).remove::<WantsGuard>();
+        }
+    }
+}
+
+fn guard(
+    mut commands: Commands,
+    players: Query<
+        (
+            Entity,
+            &Guard,
+            Has<WantsGuard>,
+            Has<Guarding>,
+            Has<GuardStun>,
+        ),
+        (
+            Ready,
+            Without<Attacking>,
+            Without<Charging>,
+            Without<Dodging>,
+            Without<Jumping>,
+        ),
+    >,
+) {
+    for (e, guard, wants, guarding, stunned) in &players {
+        if wants && !guarding {
+            commands
+                .entity(e)
+                .insert((Guarding, NoRepeat))
+                .insert(guard.clip.bundle());
+        } else if !wants && guarding && !stunned {
+            commands
+                .entity(e)
+                .remove::<(Guarding, NoRepeat)>()
+                .insert(Movement::Idle);
+        }
+    }
+}
+
+fn guard_stun(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut stunned: Query<(Entity, &mut GuardStun)>,
+) {
+    for (e, mut stun) in &mut stunned {
+        if stun.0.tick(time.delta()).finished() {
+            commands.entity(e).remove::<GuardStun>();
+        }
+    }
+}
+
+fn regen(time: Res<Time>, mut meters: Query<(&mut GuardMeter, &Guard), Without<Guarding>>) {
+    for (mut meter, guard) in &mut meters {
+        meter.0 = (meter.0 + guard.regen * time.delta_seconds()).min(guard.meter);
+    }
+}
+
+fn dizzy(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut dizzy: Query<(Entity, &mut Dizzy, &mut GuardMeter, &Guard)>,
+) {
+    for (e, mut dizzy, mut meter, guard) in &mut dizzy {
+        if dizzy.0.tick(time.delta()).finished() {
+            meter.0 = guard.meter;
+            commands.entity(e).remove::<Dizzy>().insert(Movement::Idle);
+        }
+    }
+}
+
+fn block(
+    mut commands: Commands,
+    mut hits: EventReader<HitEvent>,
+    attackers: Query<(&Hitbox, &Strike, &Transform, &Sprite)>,
+    mut victims: Query<(&Guard, &mut GuardMeter, &mut Health, &Transform, &Sprite), With<Guarding>>,
+) {
+    for hit in hits.read() {
+        let Ok((hitbox, strike, at, asprite)) = attackers.get(hit.attacker) else {
+            continue;
+        };
+        if hitbox.id != hit.hitbox_id {
+            continue;
+        }
+        let Ok((guard, mut meter, mut health, vt, vsprite)) = victims.get_mut(hit.victim) else {
+            continue;
+        };
+        if !faces(vt, vsprite, at.translation.x) {
+            continue;
+        }
+
+        // chip damage can't finish a character off
+        health.current = (health.current - strike.damage * guard.chip).max(health.current.min(1.));
+        meter.0 -= strike.damage;
+
+        let mut victim = commands.entity(hit.victim);
+        if meter.0 <= 0. {
+            meter.0 = 0.;
+            victim
+                .remove::<(GuardState, NoRepeat)>()
+                .insert(Dizzy(Timer::new(guard.dizzy, TimerMode::Once)))
+                .insert(guard.dizzy_clip.bundle());
+        } else {
+            let facing = if asprite.flip_x { -1. } else { 1. };
+            victim.insert((
+                GuardStun(Timer::new(
+                    Duration::from_millis(strike.hitstun / 2),
+                    TimerMode::Once,
+                )),
+                Knockback(Vec2::new(strike.knockback.x * facing * 0.5, 0.)),
+            ));
+        }
+    }
+}
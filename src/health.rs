@@ -0,0 +1,157 @@
+use crate::attack::{AttackState, Strike};
+use crate::charge::Charging;
+use crate::collision::{HitEvent, HitSet, Hitbox, Hurtbox};
+use crate::dodge::DodgeState;
+use crate::guard::{faces, GuardState, Guarding};
+use crate::jump::Jumping;
+use crate::physics::{Height, Knockback, KnockedDown, Launched, VerticalVelocity};
+use crate::player::{Alive, Direction, Movement};
+use crate::sprite_sheet::{Animation, AnimationEnded, Clip, NoRepeat};
+use crate::GameState;
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub struct HealthPlugin;
+
+/// This plugin turns hits into damage and plays the hurt and death reactions
+/// A character whose health runs out loses its [`Alive`] marker and a [`CharacterDied`] is sent
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CharacterDied>().add_systems(
+            Update,
+            (take_damage, recover)
+                .chain()
+                .after(HitSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Health {
+        Health { current: max, max }
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct HurtClips {
+    pub hurt: Clip,
+    pub dead: Clip,
+    pub get_up: Clip,
+}
+
+/// Hitstun, the character can't act until the timer ends
+#[derive(Component)]
+pub struct Hurt(pub Timer);
+
+#[derive(Component)]
+pub struct Dead;
+
+#[derive(Event)]
+pub struct CharacterDied(pub Entity);
+
+fn take_damage(
+    mut commands: Commands,
+    mut hits: EventReader<HitEvent>,
+    attackers: Query<(&Hitbox, &Strike, &Transform, &Sprite)>,
+    mut victims: Query<
+        (
+            &mut Health,
+            &HurtClips,
+            &Height,
+            &Transform,
+            &Sprite,
+            Has<Guarding>,
+        ),
+        (With<Alive>, Without<KnockedDown>),
+    >,
+    mut died: EventWriter<CharacterDied>,
+) {
+    for hit in hits.read() {
+        let Ok((hitbox, strike, at, sprite)) = attackers.get(hit.attacker) else {
+            continue;
+        };
+        // the strike has to be the one of the hitbox that landed, not of a swing started since
+        if hitbox.id != hit.hitbox_id {
+            continue;
+        }
+        let Ok((mut health, clips, height, vt, vsprite, guarding)) = victims.get_mut(hit.victim)
+        else {
+            continue;
+        };
+        if health.current <= 0. {
+            continue;
+        }
+        // blocked, see guard
+        if guarding && faces(vt, vsprite, at.translation.x) {
+            continue;
+        }
+
+        health.current = (health.current - strike.damage).max(0.);
+
+        let facing = if sprite.flip_x { -1. } else { 1. };
+        let mut victim = commands.entity(hit.victim);
+        victim
+            .remove::<(AttackState, GuardState, DodgeState, Charging, Jumping)>()
+            .insert((
+                NoRepeat,
+                Direction(-facing, 0.),
+                Knockback(strike.knockback * Vec2::new(facing, 1.)),
+            ));
+
+        // hits in the air always knock down
+        if strike.launch > 0. || height.0 > 0. {
+            victim.insert(Launched {
+                down: Duration::from_millis(strike.knockdown),
+            });
+        }
+        if strike.launch > 0. {
+            victim.insert(VerticalVelocity(strike.launch));
+        }
+
+        if health.current > 0. {
+            victim
+                .insert(Hurt(Timer::new(
+                    Duration::from_millis(strike.hitstun),
+                    TimerMode::Once,
+                )))
+                .insert(clips.hurt.bundle());
+        } else {
+            victim
+                .remove::<(Alive, Hurt, Hurtbox, Launched)>()
+                .insert(Dead)
+                .insert(clips.dead.bundle());
+            died.send(CharacterDied(hit.victim));
+        }
+    }
+}
+
+fn recover(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ended: EventReader<AnimationEnded>,
+    mut hurt: Query<(Entity, &mut Hurt), Without<Launched>>,
+    dead: Query<(), With<Dead>>,
+) {
+    for (e, mut hurt) in &mut hurt {
+        if hurt.0.tick(time.delta()).finished() {
+            commands
+                .entity(e)
+                .remove::<(Hurt, NoRepeat)>()
+                .insert(Movement::Idle);
+        }
+    }
+
+    for &AnimationEnded(e) in ended.read() {
+        if dead.contains(e) {
+            // stay on the last frame
+            commands.entity(e).remove::<Animation>();
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::enemy::Enemy;
+use crate::health::Health;
+use crate::player::{Character, Controller2};
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct HudPlugin;
+
+/// This plugin shows a health bar for every player, the first one on the left of the screen and the second on the right
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (add_hud, update_bars).run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+const BAR_WIDTH: f32 = 240.;
+const BAR_HEIGHT: f32 = 14.;
+const MARGIN: f32 = 16.;
+
+/// Fill of the health bar of a player
+#[derive(Component)]
+struct HealthBar(Entity);
+
+fn add_hud(
+    mut commands: Commands,
+    players: Query<(Entity, Has<Controller2>), (Added<Health>, With<Character>, Without<Enemy>)>,
+) {
+    for (player, second) in &players {
+        let (label, left, right) = if second {
+            ("P2", Val::Auto, Val::Px(MARGIN))
+        } else {
+            ("P1", Val::Px(MARGIN), Val::Auto)
+        };
+        commands
+            .spawn((
+                Name::new("Hud"),
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(MARGIN),
+                        left,
+                        right,
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::linear_rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ));
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(BAR_WIDTH),
+                            height: Val::Px(BAR_HEIGHT),
+                            ..default()
+                        },
+                        background_color: Color::linear_rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    })
+                    .with_children(|bar| {
+                        bar.spawn((
+                            HealthBar(player),
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(100.),
+                                    height: Val::Percent(100.),
+                                    ..default()
+                                },
+                                background_color: Color::linear_rgb(0.8, 0.1, 0.1).into(),
+                                ..default()
+                            },
+                        ));
+                    });
+            });
+    }
+}
+
+fn update_bars(mut bars: Query<(&HealthBar, &mut Style)>, players: Query<&Health>) {
+    for (&HealthBar(player), mut style) in &mut bars {
+        let Ok(health) = players.get(player) else {
+            continue;
+        };
+        let width = Val::Percent(100. * health.current / health.max);
+        if style.width != width {
+            style.width = width;
+        }
+    }
+}
@@ -1,18 +1,41 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
 use std::time::Duration;
 
 use bevy::{core_pipeline::core_2d::graph::input, input::*, prelude::*};
 use keyboard::KeyboardInput;
+use serde::{Deserialize, Serialize};
 
+use crate::motion::{Motion, SpecialInput};
+use crate::player::{Character, Controller, Controller1, Controller2};
 use crate::GameState;
 
 pub struct PlayerInput;
 impl Plugin for PlayerInput {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app
+        app.init_resource::<InputBuffer<Controller1>>()
+            .init_resource::<InputBuffer<Controller2>>()
             // .add_systems(Startup, init )
             .add_systems(
                 PreUpdate,
-                (keyboard_action, keyboard_analog, mouse_action)
+                (
+                    assign_pads,
+                    (
+                        keyboard_action,
+                        keyboard_analog,
+                        mouse_action,
+                        gamepad_action,
+                        gamepad_analog,
+                        touch_joystick,
+                        touch_button,
+                    ),
+                    hold,
+                    (record::<Controller1>, record::<Controller2>),
+                    (run_switch::<Controller1>, run_switch::<Controller2>),
+                )
+                    .chain()
+                    .after(InputSystem)
                     .run_if(in_state(GameState::Playing)),
             )
             .add_systems(PostUpdate, clear);
@@ -31,8 +54,163 @@ pub struct MouseAction(pub MouseButton);
 #[derive(Component, Debug)]
 pub struct Analog(pub f32, pub f32);
 
+/// Four keys read as an analog from the keys held, opposite keys held together are resolved by the [`Socd`]
 #[derive(Component)]
-pub struct KeyboardAnalog(pub KeyCode, pub KeyCode, pub KeyCode, pub KeyCode);
+pub struct KeyboardAnalog {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub right: KeyCode,
+    pub left: KeyCode,
+    pub socd: Socd,
+    x: KeyAxis,
+    y: KeyAxis,
+}
+
+impl KeyboardAnalog {
+    pub fn new(up: KeyCode, down: KeyCode, right: KeyCode, left: KeyCode, socd: Socd) -> Self {
+        KeyboardAnalog {
+            up,
+            down,
+            right,
+            left,
+            socd,
+            x: KeyAxis::default(),
+            y: KeyAxis::default(),
+        }
+    }
+
+    fn read(&mut self, keys: &ButtonInput<KeyCode>) -> (f32, f32) {
+        (
+            self.x.read(keys, self.right, self.left, self.socd),
+            self.y.read(keys, self.up, self.down, self.socd),
+        )
+    }
+}
+
+/// What two opposite keys held together do
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Socd {
+    /// The key pressed last wins, letting it go goes back to the other one
+    #[default]
+    LastInput,
+    /// They cancel out
+    Neutral,
+    /// The key pressed first wins
+    FirstInput,
+}
+
+impl Socd {
+    pub fn next(self) -> Socd {
+        match self {
+            Socd::LastInput => Socd::Neutral,
+            Socd::Neutral => Socd::FirstInput,
+            Socd::FirstInput => Socd::LastInput,
+        }
+    }
+}
+
+impl fmt::Display for Socd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Socd::LastInput => write!(f, "Last wins"),
+            Socd::Neutral => write!(f, "Neutral"),
+            Socd::FirstInput => write!(f, "First wins"),
+        }
+    }
+}
+
+/// One axis of a [`KeyboardAnalog`], remembers which of its keys went down last
+#[derive(Default, Clone, Copy)]
+struct KeyAxis {
+    last: f32,
+}
+
+impl KeyAxis {
+    fn read(
+        &mut self,
+        keys: &ButtonInput<KeyCode>,
+        positive: KeyCode,
+        negative: KeyCode,
+        socd: Socd,
+    ) -> f32 {
+        // both going down in the same frame, the positive one counts as the last
+        if keys.just_pressed(negative) {
+            self.last = -1.;
+        }
+        if keys.just_pressed(positive) {
+            self.last = 1.;
+        }
+        match (keys.pressed(positive), keys.pressed(negative), socd) {
+            (true, false, _) => 1.,
+            (false, true, _) => -1.,
+            (true, true, Socd::LastInput) => self.last,
+            (true, true, Socd::FirstInput) => -self.last,
+            _ => 0.,
+        }
+    }
+}
+
+/// Button of the [`Pad`] of the input
+#[derive(Component, PartialEq, Eq, Clone)]
+pub struct GamepadButtonAction(pub GamepadButtonType);
+
+/// Stick of the [`Pad`] of the input
+#[derive(Component)]
+pub struct GamepadStickAnalog {
+    pub x: GamepadAxisType,
+    pub y: GamepadAxisType,
+    /// Tilt under which the stick counts as centered
+    pub deadzone: f32,
+}
+
+impl GamepadStickAnalog {
+    pub fn left() -> GamepadStickAnalog {
+        GamepadStickAnalog {
+            x: GamepadAxisType::LeftStickX,
+            y: GamepadAxisType::LeftStickY,
+            deadzone: 0.25,
+        }
+    }
+}
+
+/// On-screen stick, a touch starting close to it drags it until the finger is lifted
+/// Its area is in logical window pixels from the top left corner, like touch positions
+#[derive(Component, Default)]
+pub struct TouchJoystick {
+    pub center: Vec2,
+    pub radius: f32,
+    /// Part of the radius under which the stick counts as centered
+    pub deadzone: f32,
+    touch: Option<u64>,
+}
+
+impl TouchJoystick {
+    pub fn new(deadzone: f32) -> TouchJoystick {
+        TouchJoystick {
+            deadzone,
+            ..default()
+        }
+    }
+}
+
+/// On-screen button, held as long as the touch that started on it
+#[derive(Component, Default)]
+pub struct TouchButton {
+    pub center: Vec2,
+    pub radius: f32,
+    touch: Option<u64>,
+}
+
+/// How far from its center a touch still grabs a [`TouchJoystick`], in radiuses
+const JOYSTICK_GRAB: f32 = 1.5;
+
+/// Gamepad feeding the gamepad inputs of a controller, none until one is plugged in
+#[derive(Component, Default, PartialEq)]
+pub struct Pad(pub Option<Gamepad>);
+
+/// How long an [`Active`] input has been held, kept after it is released
+#[derive(Component, Default)]
+pub struct Held(pub Duration);
 
 #[derive(Component)]
 pub struct Active;
@@ -41,6 +219,89 @@ pub struct Just;
 #[derive(Component)]
 pub struct Released;
 
+/// Presses kept in an [`InputBuffer`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Press {
+    Run,
+    Attack,
+    Guard,
+    Dodge,
+    Jump,
+    Special,
+    /// The movement went the way, one of the 8 directions or (0, 0) when let go
+    Stick(IVec2),
+}
+
+/// How many presses a buffer remembers, the oldest are dropped first
+const BUFFER_SIZE: usize = 32;
+
+/// Recent presses of a controller with the time they were made
+/// A press stays usable for the buffer window, so one made shortly before an action is possible isn't lost
+#[derive(Resource)]
+pub struct InputBuffer<C: Controller> {
+    presses: VecDeque<(Press, Duration)>,
+    /// Way of the last [`Press::Stick`]
+    stick: IVec2,
+    pub window: Duration,
+    controller: PhantomData<C>,
+}
+
+impl<C: Controller> Default for InputBuffer<C> {
+    fn default() -> Self {
+        InputBuffer {
+            presses: VecDeque::with_capacity(BUFFER_SIZE),
+            stick: IVec2::ZERO,
+            window: Duration::from_millis(150),
+            controller: PhantomData,
+        }
+    }
+}
+
+impl<C: Controller> InputBuffer<C> {
+    pub fn push(&mut self, press: Press, at: Duration) {
+        if self.presses.len() == BUFFER_SIZE {
+            self.presses.pop_front();
+        }
+        self.presses.push_back((press, at));
+    }
+
+    fn in_window(&self, at: Duration, now: Duration) -> bool {
+        at <= now && now - at <= self.window
+    }
+
+    /// A press made within the window that wasn't used yet
+    pub fn buffered(&self, press: Press, now: Duration) -> bool {
+        self.presses
+            .iter()
+            .any(|&(p, at)| p == press && self.in_window(at, now))
+    }
+
+    /// Uses up the latest press of the kind made within the window, the one [`InputBuffer::buffered`] found
+    /// Button presses that are too old to be used are dropped, movements are kept for the motion inputs
+    pub fn consume(&mut self, press: Press, now: Duration) {
+        if let Some(i) = self
+            .presses
+            .iter()
+            .rposition(|&(p, at)| p == press && self.in_window(at, now))
+        {
+            self.presses.remove(i);
+        }
+        let window = self.window;
+        self.presses
+            .retain(|&(p, at)| matches!(p, Press::Stick(_)) || at > now || now - at <= window);
+    }
+
+    /// Way of the movement, one of the 8 directions or (0, 0)
+    pub fn stick(&self) -> IVec2 {
+        self.stick
+    }
+
+    /// Presses from the oldest to the latest
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &(Press, Duration)> {
+        self.presses.iter()
+    }
+}
+
 #[derive(Component)]
 pub struct Movement;
 
@@ -50,9 +311,59 @@ pub struct Run;
 #[derive(Component)]
 pub struct Attack;
 
+/// How a controller enters [`Movement::Run`](crate::player::Movement::Run)
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum RunMode {
+    /// Runs while a run button is held
+    #[default]
+    Hold,
+    /// Runs after a [`Motion::DoubleTapForward`], until the movement is let go
+    DoubleTap,
+    /// A run button press switches between running and walking
+    Toggle,
+}
+
+impl RunMode {
+    pub fn next(self) -> RunMode {
+        match self {
+            RunMode::Hold => RunMode::DoubleTap,
+            RunMode::DoubleTap => RunMode::Toggle,
+            RunMode::Toggle => RunMode::Hold,
+        }
+    }
+}
+
+impl fmt::Display for RunMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunMode::Hold => write!(f, "Hold"),
+            RunMode::DoubleTap => write!(f, "Double tap"),
+            RunMode::Toggle => write!(f, "Toggle"),
+        }
+    }
+}
+
+/// Input active while its controller should run, fed by the [`Run`] inputs and the movement as the mode says
+#[derive(Component)]
+pub struct RunSwitch {
+    pub mode: RunMode,
+    /// Longest time between a tap and the let go around it, the step window of the double tap motion
+    pub double_tap: Duration,
+}
+
 #[derive(Component)]
 pub struct Dodge;
 
+#[derive(Component)]
+pub struct Guard;
+
+#[derive(Component)]
+pub struct Jump;
+
+/// Does a special move without its motion, see [`MotionPlugin`](crate::motion::MotionPlugin)
+#[derive(Component)]
+pub struct Special;
+
 fn keyboard_action(
     mut commands: Commands,
     inputs: Query<(Entity, &KeyboardAction)>,
@@ -73,59 +384,21 @@ fn keyboard_action(
 }
 fn keyboard_analog(
     mut commands: Commands,
-    inputs: Query<(Entity, &Analog, &KeyboardAnalog)>,
-    mut kbd: EventReader<KeyboardInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut inputs: Query<(Entity, &mut KeyboardAnalog, &mut Analog, Has<Active>)>,
 ) {
-    for (e, &Analog(current_x, current_y), &KeyboardAnalog(up, down, right, left)) in &inputs {
-        for ev in kbd.read() {
-            let y = if up == ev.key_code {
-                if ev.state == ButtonState::Pressed {
-                    1.0
-                } else if ev.state == ButtonState::Released {
-                    0.0
-                } else {
-                    current_y
-                }
-            } else if down == ev.key_code {
-                if ev.state == ButtonState::Pressed {
-                    -1.0
-                } else if ev.state == ButtonState::Released {
-                    0.0
-                } else {
-                    current_y
-                }
-            } else {
-                current_y
-            };
-
-            let x = if right == ev.key_code {
-                if ev.state == ButtonState::Pressed {
-                    1.0
-                } else if ev.state == ButtonState::Released {
-                    0.0
-                } else {
-                    current_x
-                }
-            } else if left == ev.key_code {
-                if ev.state == ButtonState::Pressed {
-                    -1.0
-                } else if ev.state == ButtonState::Released {
-                    0.0
-                } else {
-                    current_x
-                }
-            } else {
-                current_x
-            };
-
-            if x == 0. && y == 0. {
-                commands.entity(e).remove::<Active>().insert(Released);
-            } else {
-                commands.entity(e).insert(Active).insert(Just);
-            }
+    for (e, mut keyboard, mut analog, active) in &mut inputs {
+        let (x, y) = keyboard.read(&keys);
+        if x == analog.0 && y == analog.1 {
+            continue;
+        }
 
-            commands.entity(e).insert(Analog(x, y));
+        if x == 0. && y == 0. {
+            commands.entity(e).remove::<Active>().insert(Released);
+        } else if !active {
+            commands.entity(e).insert(Active).insert(Just);
         }
+        *analog = Analog(x, y);
     }
 }
 
@@ -143,6 +416,255 @@ fn mouse_action(
     }
 }
 
+fn gamepad_action(
+    mut commands: Commands,
+    inputs: Query<(Entity, &Pad, &GamepadButtonAction)>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+) {
+    for (e, pad, &GamepadButtonAction(button)) in &inputs {
+        let Some(gamepad) = pad.0 else {
+            continue;
+        };
+        let button = GamepadButton::new(gamepad, button);
+        if buttons.just_pressed(button) {
+            commands.entity(e).insert(Active).insert(Just);
+        } else if buttons.just_released(button) {
+            commands.entity(e).insert(Released).remove::<Active>();
+        }
+    }
+}
+
+fn gamepad_analog(
+    mut commands: Commands,
+    inputs: Query<(Entity, &Pad, &Analog, &GamepadStickAnalog, Has<Active>)>,
+    axes: Res<Axis<GamepadAxis>>,
+) {
+    for (e, pad, &Analog(current_x, current_y), stick, active) in &inputs {
+        let Some(gamepad) = pad.0 else {
+            continue;
+        };
+        let x = axes.get(GamepadAxis::new(gamepad, stick.x)).unwrap_or(0.);
+        let y = axes.get(GamepadAxis::new(gamepad, stick.y)).unwrap_or(0.);
+        let (x, y) = if Vec2::new(x, y).length() < stick.deadzone {
+            (0., 0.)
+        } else {
+            (x, y)
+        };
+        if x == current_x && y == current_y {
+            continue;
+        }
+
+        if x == 0. && y == 0. {
+            commands.entity(e).remove::<Active>().insert(Released);
+        } else if !active {
+            commands.entity(e).insert(Active).insert(Just);
+        }
+        commands.entity(e).insert(Analog(x, y));
+    }
+}
+
+fn touch_joystick(
+    mut commands: Commands,
+    touches: Res<Touches>,
+    mut inputs: Query<(Entity, &mut TouchJoystick, &Analog, Has<Active>)>,
+) {
+    for (e, mut stick, &Analog(current_x, current_y), active) in &mut inputs {
+        if stick.touch.is_none() {
+            stick.touch = touches
+                .iter_just_pressed()
+                .find(|touch| {
+                    touch.position().distance(stick.center) <= stick.radius * JOYSTICK_GRAB
+                })
+                .map(|touch| touch.id());
+        }
+        let held = stick.touch.and_then(|id| touches.get_pressed(id));
+        if held.is_none() {
+            stick.touch = None;
+        }
+
+        // screen Y points down
+        let offset = held
+            .map(|touch| (touch.position() - stick.center) / stick.radius)
+            .unwrap_or_default()
+            .clamp_length_max(1.)
+            * Vec2::new(1., -1.);
+        let (x, y) = if offset.length() < stick.deadzone {
+            (0., 0.)
+        } else {
+            (offset.x, offset.y)
+        };
+        if x == current_x && y == current_y {
+            continue;
+        }
+
+        if x == 0. && y == 0. {
+            commands.entity(e).remove::<Active>().insert(Released);
+        } else if !active {
+            commands.entity(e).insert(Active).insert(Just);
+        }
+        commands.entity(e).insert(Analog(x, y));
+    }
+}
+
+fn touch_button(
+    mut commands: Commands,
+    touches: Res<Touches>,
+    mut inputs: Query<(Entity, &mut TouchButton)>,
+) {
+    for (e, mut button) in &mut inputs {
+        match button.touch {
+            None => {
+                let Some(touch) = touches
+                    .iter_just_pressed()
+                    .find(|touch| touch.position().distance(button.center) <= button.radius)
+                else {
+                    continue;
+                };
+                button.touch = Some(touch.id());
+                commands.entity(e).insert(Active).insert(Just);
+            }
+            Some(id) if touches.get_pressed(id).is_none() => {
+                button.touch = None;
+                commands.entity(e).insert(Released).remove::<Active>();
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Hands the plugged in gamepads to the controllers, the first player gets the first one
+/// A controller whose pad is unplugged gets the next one plugged in
+/// Inputs of a pad that is unplugged or handed over are let go, they won't see the release
+fn assign_pads(
+    mut commands: Commands,
+    gamepads: Res<Gamepads>,
+    mut pads: Query<(Entity, &mut Pad, Has<Controller2>, Has<Active>, Has<Analog>)>,
+) {
+    let mut taken = [None, None];
+    for (_, pad, second, ..) in &pads {
+        if pad.0.is_some_and(|gamepad| gamepads.contains(gamepad)) {
+            taken[second as usize] = pad.0;
+        }
+    }
+    let held = taken;
+    let mut free = gamepads.iter().filter(|&g| !held.contains(&Some(g)));
+    for slot in &mut taken {
+        if slot.is_none() {
+            *slot = free.next();
+        }
+    }
+    for (e, mut pad, second, active, analog) in &mut pads {
+        let gamepad = taken[second as usize];
+        if pad.0 == gamepad {
+            continue;
+        }
+        if pad.0.is_some() {
+            if active {
+                commands.entity(e).remove::<Active>().insert(Released);
+            }
+            if analog {
+                commands.entity(e).insert(Analog(0., 0.));
+            }
+        }
+        pad.0 = gamepad;
+    }
+}
+
+/// Stick tilt from which a direction counts
+const STICK_THRESHOLD: f32 = 0.5;
+
+fn record<C: Controller>(
+    time: Res<Time>,
+    mut buffer: ResMut<InputBuffer<C>>,
+    sticks: Query<&Analog, (With<C>, With<Movement>, Changed<Analog>)>,
+    pressed: Query<
+        (
+            Has<Run>,
+            Has<Attack>,
+            Has<Guard>,
+            Has<Dodge>,
+            Has<Jump>,
+            Has<Special>,
+        ),
+        (With<C>, With<Just>),
+    >,
+) {
+    for &Analog(x, y) in &sticks {
+        let way = |v: f32| {
+            if v.abs() < STICK_THRESHOLD {
+                0
+            } else {
+                v.signum() as i32
+            }
+        };
+        let stick = IVec2::new(way(x), way(y));
+        if stick != buffer.stick {
+            buffer.stick = stick;
+            buffer.push(Press::Stick(stick), time.elapsed());
+        }
+    }
+
+    for (run, attack, guard, dodge, jump, special) in &pressed {
+        let press = match (run, attack, guard, dodge, jump, special) {
+            (true, ..) => Press::Run,
+            (_, true, ..) => Press::Attack,
+            (_, _, true, ..) => Press::Guard,
+            (_, _, _, true, ..) => Press::Dodge,
+            (.., true, _) => Press::Jump,
+            (.., true) => Press::Special,
+            _ => continue,
+        };
+        buffer.push(press, time.elapsed());
+    }
+}
+
+/// Double taps come from the motions recognised last frame
+fn run_switch<C: Controller>(
+    mut commands: Commands,
+    mut specials: EventReader<SpecialInput>,
+    characters: Query<(), (With<C>, With<Character>)>,
+    switches: Query<(Entity, &RunSwitch, Has<Active>), With<C>>,
+    buttons: Query<(Has<Active>, Has<Just>), (With<C>, With<Run>)>,
+    moving: Query<(), (With<C>, With<Movement>, With<Active>)>,
+) {
+    let held = buttons.iter().any(|(active, _)| active);
+    let pressed = buttons.iter().any(|(_, just)| just);
+    let double_tapped = specials
+        .read()
+        .filter(|special| {
+            special.motion == Motion::DoubleTapForward && characters.contains(special.character)
+        })
+        .count()
+        > 0;
+    for (e, switch, running) in &switches {
+        let run = match switch.mode {
+            RunMode::Hold => held,
+            RunMode::Toggle => running != pressed,
+            RunMode::DoubleTap => !moving.is_empty() && (running || double_tapped),
+        };
+        if run && !running {
+            commands.entity(e).insert(Active).insert(Just);
+        } else if !run && running {
+            commands.entity(e).insert(Released).remove::<Active>();
+        }
+    }
+}
+
+fn hold(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut inputs: Query<(Entity, Option<&mut Held>, Has<Just>), With<Active>>,
+) {
+    for (e, held, just) in &mut inputs {
+        match held {
+            Some(mut held) if !just => held.0 += time.delta(),
+            _ => {
+                commands.entity(e).insert(Held::default());
+            }
+        }
+    }
+}
+
 fn clear(mut commands: Commands, mut inputs: Query<Entity, With<Input>>) {
     for e in &mut inputs {
         commands.entity(e).remove::<Just>().remove::<Released>();
@@ -154,3 +676,122 @@ fn clear(mut commands: Commands, mut inputs: Query<Entity, With<Input>>) {
         // }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<ButtonInput<KeyCode>>()
+            .add_systems(Update, keyboard_analog);
+        app
+    }
+
+    fn spawn(app: &mut App, keys: [KeyCode; 4], socd: Socd) -> Entity {
+        let [up, down, right, left] = keys;
+        app.world_mut()
+            .spawn((
+                Analog(0., 0.),
+                KeyboardAnalog::new(up, down, right, left, socd),
+            ))
+            .id()
+    }
+
+    const WASD: [KeyCode; 4] = [KeyCode::KeyW, KeyCode::KeyS, KeyCode::KeyD, KeyCode::KeyA];
+    const ARROWS: [KeyCode; 4] = [
+        KeyCode::ArrowUp,
+        KeyCode::ArrowDown,
+        KeyCode::ArrowRight,
+        KeyCode::ArrowLeft,
+    ];
+
+    /// Runs a frame after the keys went down and up
+    fn frame(app: &mut App, press: &[KeyCode], release: &[KeyCode]) {
+        let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keys.clear();
+        for &key in press {
+            keys.press(key);
+        }
+        for &key in release {
+            keys.release(key);
+        }
+        app.update();
+    }
+
+    fn analog(app: &App, e: Entity) -> (f32, f32) {
+        let &Analog(x, y) = app.world().get::<Analog>(e).unwrap();
+        (x, y)
+    }
+
+    #[test]
+    fn released_key_goes_back_to_the_held_one() {
+        let mut app = app();
+        let e = spawn(&mut app, WASD, Socd::LastInput);
+        frame(&mut app, &[KeyCode::KeyA], &[]);
+        assert_eq!(analog(&app, e), (-1., 0.));
+        frame(&mut app, &[KeyCode::KeyD], &[]);
+        assert_eq!(analog(&app, e), (1., 0.));
+        frame(&mut app, &[], &[KeyCode::KeyD]);
+        assert_eq!(analog(&app, e), (-1., 0.));
+        frame(&mut app, &[], &[KeyCode::KeyA]);
+        assert_eq!(analog(&app, e), (0., 0.));
+    }
+
+    #[test]
+    fn neutral_cancels_opposite_keys() {
+        let mut app = app();
+        let e = spawn(&mut app, WASD, Socd::Neutral);
+        frame(&mut app, &[KeyCode::KeyW], &[]);
+        frame(&mut app, &[KeyCode::KeyS], &[]);
+        assert_eq!(analog(&app, e), (0., 0.));
+        frame(&mut app, &[], &[KeyCode::KeyW]);
+        assert_eq!(analog(&app, e), (0., -1.));
+    }
+
+    #[test]
+    fn first_input_keeps_the_first_key() {
+        let mut app = app();
+        let e = spawn(&mut app, WASD, Socd::FirstInput);
+        frame(&mut app, &[KeyCode::KeyA], &[]);
+        frame(&mut app, &[KeyCode::KeyD], &[]);
+        assert_eq!(analog(&app, e), (-1., 0.));
+        frame(&mut app, &[], &[KeyCode::KeyA]);
+        assert_eq!(analog(&app, e), (1., 0.));
+    }
+
+    #[test]
+    fn axes_are_independent() {
+        let mut app = app();
+        let e = spawn(&mut app, WASD, Socd::LastInput);
+        frame(&mut app, &[KeyCode::KeyW, KeyCode::KeyD], &[]);
+        assert_eq!(analog(&app, e), (1., 1.));
+        frame(&mut app, &[KeyCode::KeyA], &[KeyCode::KeyW]);
+        assert_eq!(analog(&app, e), (-1., 0.));
+    }
+
+    #[test]
+    fn every_keyboard_analog_reads_its_own_keys() {
+        let mut app = app();
+        let wasd = spawn(&mut app, WASD, Socd::LastInput);
+        let arrows = spawn(&mut app, ARROWS, Socd::LastInput);
+        frame(&mut app, &[KeyCode::KeyA, KeyCode::ArrowUp], &[]);
+        assert_eq!(analog(&app, wasd), (-1., 0.));
+        assert_eq!(analog(&app, arrows), (0., 1.));
+        frame(&mut app, &[], &[KeyCode::KeyA]);
+        assert_eq!(analog(&app, wasd), (0., 0.));
+        assert_eq!(analog(&app, arrows), (0., 1.));
+    }
+
+    #[test]
+    fn active_while_any_key_is_held() {
+        let mut app = app();
+        let e = spawn(&mut app, WASD, Socd::LastInput);
+        frame(&mut app, &[KeyCode::KeyD], &[]);
+        assert!(app.world().get::<Active>(e).is_some());
+        assert!(app.world().get::<Just>(e).is_some());
+        frame(&mut app, &[], &[KeyCode::KeyD]);
+        assert!(app.world().get::<Active>(e).is_none());
+        assert!(app.world().get::<Released>(e).is_some());
+    }
+}
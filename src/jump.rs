@@ -0,0 +1,132 @@
+use crate::attack::AttackState;
+use crate::charge::Charging;
+use crate::physics::VerticalVelocity;
+use crate::player::{
+    ActionSet, Character, Controller, Controller1, Controller2, Direction, Free, LevelLimit,
+    MoveSpeed, Movement, ReadInputSet,
+};
+use crate::sprite_sheet::{Clip, NoRepeat};
+use crate::{input, GameState};
+use bevy::prelude::*;
+
+pub struct JumpPlugin;
+
+/// This plugin handles jumping, the character leaves the floor on the height axis and drifts the way it was moving
+/// It can't steer in the air but can attack once, the jump ends when it lands
+impl Plugin for JumpPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (jump_input::<Controller1>, jump_input::<Controller2>)
+                .in_set(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (jump, drift.run_if(resource_exists::<LevelLimit>), land)
+                .chain()
+                .in_set(ActionSet)
+                .after(ReadInputSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct Jump {
+    pub clip: Clip,
+    /// Vertical velocity at take off
+    pub speed: f32,
+}
+
+/// Intent to jump, set by a controller and consumed by [`jump`]
+#[derive(Component)]
+pub struct WantsJump;
+
+#[derive(Component)]
+pub struct Jumping {
+    /// Floor velocity kept from the take off
+    velocity: Vec2,
+    /// The air attack was used
+    pub attacked: bool,
+}
+
+/// Asks to jump as long as a buffered press wasn't used by a jump
+fn jump_input<C: Controller>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut buffer: ResMut<input::InputBuffer<C>>,
+    players: Query<(Entity, Option<Ref<Jumping>>), (With<C>, With<Character>)>,
+) {
+    for (e, jumping) in &players {
+        if jumping.is_some_and(|jumping| jumping.is_added()) {
+            buffer.consume(input::Press::Jump, time.elapsed());
+        }
+        if buffer.buffered(input::Press::Jump, time.elapsed()) {
+            commands.entity(e).insert(WantsJump);
+        }
+    }
+}
+
+fn jump(
+    mut commands: Commands,
+    players: Query<(Entity, Option<(&Jump, &Movement, &MoveSpeed, &Direction)>), With<WantsJump>>,
+    free: Query<(), (Free, Without<Charging>, Without<VerticalVelocity>)>,
+) {
+    for (e, jump) in &players {
+        commands.entity(e).remove::<WantsJump>();
+
+        let Some((jump, &movement, speed, &Direction(x, y))) = jump else {
+            continue;
+        };
+        if !free.contains(e) {
+            continue;
+        }
+
+        let speed = match movement {
+            Movement::Idle => 0.,
+            Movement::Walk => speed.walk,
+            Movement::Run => speed.run,
+        };
+        let velocity = if speed == 0. || (x == 0. && y == 0.) {
+            Vec2::ZERO
+        } else {
+            Vec2::new(x, y * 0.6).normalize() * speed
+        };
+
+        commands
+            .entity(e)
+            .insert((
+                Jumping {
+                    velocity,
+                    attacked: false,
+                },
+                VerticalVelocity(jump.speed),
+                NoRepeat,
+            ))
+            .insert(jump.clip.bundle());
+    }
+}
+
+fn drift(time: Res<Time>, limit: Res<LevelLimit>, mut players: Query<(&mut Transform, &Jumping)>) {
+    let LevelLimit(bounds) = *limit;
+    for (mut t, jumping) in &mut players {
+        let step = jumping.velocity * time.delta_seconds();
+        t.translation.x = (t.translation.x + step.x).clamp(bounds.min.x, bounds.max.x);
+        t.translation.y = (t.translation.y + step.y).clamp(bounds.min.y, bounds.max.y);
+    }
+}
+
+/// Back on the floor, an air attack still going is cut short
+fn land(
+    mut commands: Commands,
+    players: Query<Entity, (With<Jumping>, Without<VerticalVelocity>)>,
+) {
+    for e in &players {
+        commands
+            .entity(e)
+            .remove::<(Jumping, NoRepeat)>()
+            .remove::<AttackState>()
+            .insert(Movement::Idle);
+    }
+}
@@ -1,42 +1,295 @@
-use bevy::{prelude::*, render::camera::ScalingMode};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::sprite::{Anchor, ImageScaleMode};
+use serde::Deserialize;
+use thiserror::Error;
 
-use crate::{assets::TextureAssets, GameState};
+use crate::character::CharacterDef;
+use crate::depth::YSort;
+use crate::parallax::ParallaxLayer;
+use crate::player::LevelLimit;
+use crate::{assets::LevelAssets, GameState};
 
 pub struct LevelPlugin;
 
+/// This plugin builds the stage of the current level, its background, props and floor bounds
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, add_camera)
-            .add_systems(OnEnter(GameState::Playing), add_bg);
+        app.init_resource::<CurrentLevel>()
+            .add_systems(OnEnter(GameState::Playing), build_level);
     }
 }
 
-fn add_camera(mut commands: Commands) {
-    commands.spawn(Camera2dBundle {
-        transform: Transform::from_xyz(0.0, 0.0, 100.),
-        projection: OrthographicProjection {
-            scaling_mode: ScalingMode::FixedVertical(270.),
-
-            ..default()
-        },
-        ..default()
-    });
-}
-
-fn add_bg(mut commands: Commands, assets: Res<TextureAssets>) {
-    println!("test");
-    commands.spawn(SpriteBundle {
-        texture: assets.bg.clone(),
-        ..default()
-    });
-    commands.spawn(SpriteBundle {
-        transform: Transform::from_xyz(-480., 0., 0.),
-        texture: assets.bg.clone(),
-        ..default()
-    });
-    commands.spawn(SpriteBundle {
-        transform: Transform::from_xyz(480., 0., 0.),
-        texture: assets.bg.clone(),
-        ..default()
-    });
+/// Index in [`LevelAssets`] of the level being played
+#[derive(Resource, Default)]
+pub struct CurrentLevel(pub usize);
+
+/// Definition of the level being played
+#[derive(SystemParam)]
+pub struct Level<'w> {
+    levels: Res<'w, LevelAssets>,
+    current: Res<'w, CurrentLevel>,
+    defs: Res<'w, Assets<LevelDef>>,
+}
+
+impl Level<'_> {
+    pub fn def(&self) -> Option<&LevelDef> {
+        self.levels
+            .levels
+            .get(self.current.0)
+            .and_then(|handle| self.defs.get(handle))
+    }
+}
+
+fn build_level(mut commands: Commands, level: Level) {
+    let Some(def) = level.def() else {
+        return;
+    };
+    commands.insert_resource(LevelLimit(def.floor));
+
+    for layer in &def.layers {
+        let mut entity = commands.spawn((
+            Name::new("Layer"),
+            layer.parallax.clone(),
+            SpriteBundle {
+                transform: Transform::from_xyz(layer.parallax.x, layer.offset, layer.z)
+                    .with_scale(Vec3::new(layer.scale, layer.scale, 1.)),
+                texture: layer.image.clone(),
+                ..default()
+            },
+        ));
+        if layer.parallax.repeat {
+            entity.insert(ImageScaleMode::Tiled {
+                tile_x: true,
+                tile_y: false,
+                stretch_value: 1.,
+            });
+        }
+    }
+    for prop in &def.props {
+        commands.spawn((
+            Name::new("Prop"),
+            YSort::default(),
+            SpriteBundle {
+                transform: Transform::from_xyz(prop.at.x, prop.at.y, 0.),
+                texture: prop.image.clone(),
+                sprite: Sprite {
+                    anchor: Anchor::BottomCenter,
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+}
+
+/// A stage, loaded from a `.level.ron` file by [`LevelDefLoader`]
+#[derive(Asset, TypePath)]
+pub struct LevelDef {
+    /// Walkable floor, from the start to the end of the stage and between the top and bottom of the band
+    pub floor: Rect,
+    /// Where the players start, one per player
+    pub spawns: Vec<Vec2>,
+    pub layers: Vec<Layer>,
+    pub props: Vec<Prop>,
+    /// In the order they are met along the stage
+    pub encounters: Vec<Encounter>,
+}
+
+/// A background image, centered on its position and scrolled by the camera
+pub struct Layer {
+    pub image: Handle<Image>,
+    pub parallax: ParallaxLayer,
+    /// Vertical position
+    pub offset: f32,
+    /// Size of the image in the stage, for art drawn at another resolution
+    pub scale: f32,
+    /// Drawing order, lower layers are further back
+    pub z: f32,
+}
+
+/// A decoration standing on the floor, its position is the bottom of the image
+pub struct Prop {
+    pub image: Handle<Image>,
+    pub at: Vec2,
+}
+
+pub struct Encounter {
+    /// Floor X a player has to reach to start the encounter
+    pub trigger: f32,
+    /// Floor X range the players are held in until the last wave is defeated
+    pub lock: (f32, f32),
+    pub waves: Vec<Wave>,
+}
+
+pub struct Wave {
+    pub enemies: Vec<EnemySpawn>,
+}
+
+pub struct EnemySpawn {
+    pub character: Handle<CharacterDef>,
+    pub entry: Entry,
+    /// Floor position the enemy ends its entrance at
+    pub at: Vec2,
+}
+
+/// How an enemy comes into the locked screen
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Entry {
+    /// Walks in from past the left of the lock
+    Left,
+    /// Walks in from past the right of the lock
+    Right,
+    /// Falls from above the screen
+    Drop,
+}
+
+#[derive(Default)]
+pub struct LevelDefLoader;
+
+#[derive(Debug, Error)]
+pub enum LevelDefLoaderError {
+    #[error("could not read level file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse level file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for LevelDefLoader {
+    type Asset = LevelDef;
+    type Settings = ();
+    type Error = LevelDefLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<LevelDef, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: LevelFile = ron::de::from_bytes(&bytes)?;
+
+        Ok(LevelDef {
+            floor: Rect::new(file.length.0, file.floor.0, file.length.1, file.floor.1),
+            spawns: file.spawns.iter().map(|&(x, y)| Vec2::new(x, y)).collect(),
+            layers: file
+                .layers
+                .into_iter()
+                .map(|layer| Layer {
+                    image: load_context.load(layer.image),
+                    parallax: ParallaxLayer {
+                        scroll: layer.scroll,
+                        repeat: layer.repeat,
+                        x: layer.x,
+                    },
+                    offset: layer.offset,
+                    scale: layer.scale,
+                    z: layer.z,
+                })
+                .collect(),
+            props: file
+                .props
+                .into_iter()
+                .map(|prop| Prop {
+                    image: load_context.load(prop.image),
+                    at: Vec2::new(prop.at.0, prop.at.1),
+                })
+                .collect(),
+            encounters: file
+                .encounters
+                .into_iter()
+                .map(|encounter| Encounter {
+                    trigger: encounter.trigger,
+                    lock: encounter.lock,
+                    waves: encounter
+                        .waves
+                        .into_iter()
+                        .map(|wave| Wave {
+                            enemies: wave
+                                .enemies
+                                .into_iter()
+                                .map(|enemy| EnemySpawn {
+                                    character: load_context.load(enemy.character),
+                                    entry: enemy.entry,
+                                    at: Vec2::new(enemy.at.0, enemy.at.1),
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+// the following types mirror the ones above as they are written in the RON files
+
+#[derive(Deserialize)]
+struct LevelFile {
+    /// First and last floor X of the stage
+    length: (f32, f32),
+    /// Lowest and highest floor Y
+    floor: (f32, f32),
+    spawns: Vec<(f32, f32)>,
+    layers: Vec<LayerFile>,
+    #[serde(default)]
+    props: Vec<PropFile>,
+    encounters: Vec<EncounterFile>,
+}
+
+#[derive(Deserialize)]
+struct LayerFile {
+    image: String,
+    #[serde(default = "default_scroll")]
+    scroll: f32,
+    #[serde(default)]
+    repeat: bool,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    offset: f32,
+    #[serde(default = "default_scale")]
+    scale: f32,
+    z: f32,
+}
+
+fn default_scroll() -> f32 {
+    1.
+}
+
+fn default_scale() -> f32 {
+    1.
+}
+
+#[derive(Deserialize)]
+struct PropFile {
+    image: String,
+    at: (f32, f32),
+}
+
+#[derive(Deserialize)]
+struct EncounterFile {
+    trigger: f32,
+    lock: (f32, f32),
+    waves: Vec<WaveFile>,
+}
+
+#[derive(Deserialize)]
+struct WaveFile {
+    enemies: Vec<EnemyFile>,
+}
+
+#[derive(Deserialize)]
+struct EnemyFile {
+    /// Path of the character definition
+    character: String,
+    entry: Entry,
+    at: (f32, f32),
 }
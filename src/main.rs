@@ -6,15 +6,54 @@ use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use input::PlayerInput;
 use level::LevelPlugin;
 mod assets;
+mod attack;
+mod bindings;
+mod camera;
+mod character;
+mod charge;
+mod collision;
+mod controls;
+mod crowd;
+mod depth;
+mod dodge;
+mod encounter;
+mod enemy;
+mod guard;
+mod health;
+mod hud;
 mod input;
+mod jump;
 mod level;
 mod menu;
+mod motion;
+mod parallax;
+mod physics;
 mod player;
 mod sprite_sheet;
+mod touch;
 use crate::assets::AssetsPlugin;
+use crate::attack::AttackPlugin;
+use crate::bindings::BindingsPlugin;
+use crate::camera::CameraPlugin;
+use crate::charge::ChargePlugin;
+use crate::collision::CollisionPlugin;
+use crate::controls::ControlsPlugin;
+use crate::crowd::CrowdPlugin;
+use crate::depth::DepthPlugin;
+use crate::dodge::DodgePlugin;
+use crate::encounter::EncounterPlugin;
+use crate::enemy::EnemyPlugin;
+use crate::guard::GuardPlugin;
+use crate::health::HealthPlugin;
+use crate::hud::HudPlugin;
+use crate::jump::JumpPlugin;
 use crate::menu::MenuPlugin;
+use crate::motion::MotionPlugin;
+use crate::parallax::ParallaxPlugin;
+use crate::physics::PhysicsPlugin;
 use crate::player::PlayerPlugin;
 use crate::sprite_sheet::SpriteSheetPlugin;
+use crate::touch::TouchPlugin;
 use bevy::input::common_conditions::input_toggle_active;
 use bevy::prelude::*;
 use bevy::window::{PrimaryWindow, WindowResolution};
@@ -61,9 +100,30 @@ fn main() {
             SpriteSheetPlugin,
             AssetsPlugin,
             MenuPlugin,
+            BindingsPlugin,
+            ControlsPlugin,
             LevelPlugin,
+            CameraPlugin,
+            ParallaxPlugin,
             PlayerPlugin,
+            EnemyPlugin,
+            CrowdPlugin,
+            EncounterPlugin,
             PlayerInput,
+            TouchPlugin,
+        ))
+        .add_plugins((
+            AttackPlugin,
+            ChargePlugin,
+            CollisionPlugin,
+            HealthPlugin,
+            GuardPlugin,
+            DodgePlugin,
+            JumpPlugin,
+            MotionPlugin,
+            PhysicsPlugin,
+            DepthPlugin,
+            HudPlugin,
         ))
         .insert_state(GameState::Loading);
 
@@ -109,4 +169,6 @@ enum GameState {
     Playing,
     // Here the menu is drawn and waiting for player interaction
     Menu,
+    // Here the key bindings are shown and can be changed
+    Controls,
 }
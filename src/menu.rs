@@ -1,16 +1,26 @@
-use crate::{assets::TextureAssets, GameState};
+use crate::{assets::TextureAssets, collision::FriendlyFire, player::Fighter, GameState};
 use bevy::prelude::*;
 
 pub struct MenuPlugin;
 
-/// This plugin is responsible for the game menu (containing only one button...)
+/// This plugin is responsible for the game menu (one play button per character, the co-op settings and the controls)
 /// The menu is only drawn during the State `GameState::Menu` and is removed when that state is exited
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Menu), setup_menu)
             .add_systems(
                 Update,
-                (click_play_button, hover_button).run_if(in_state(GameState::Menu)),
+                (
+                    click_play_button,
+                    click_friendly_fire,
+                    click_controls_button,
+                )
+                    .run_if(in_state(GameState::Menu)),
+            )
+            .add_systems(
+                Update,
+                hover_button
+                    .run_if(in_state(GameState::Menu).or_else(in_state(GameState::Controls))),
             )
             .add_systems(OnExit(GameState::Menu), cleanup_menu);
     }
@@ -34,9 +44,17 @@ impl Default for ButtonColors {
 #[derive(Component)]
 struct Menu;
 #[derive(Component)]
-struct PlayButton;
+struct PlayButton(Fighter);
+#[derive(Component)]
+struct FriendlyFireButton;
+#[derive(Component)]
+struct ControlsButton;
 
-fn setup_menu(mut commands: Commands, assets: Res<TextureAssets>) {
+fn setup_menu(
+    mut commands: Commands,
+    assets: Res<TextureAssets>,
+    friendly_fire: Res<FriendlyFire>,
+) {
     info!("menu");
     commands.spawn((
         Menu,
@@ -62,33 +80,18 @@ fn setup_menu(mut commands: Commands, assets: Res<TextureAssets>) {
             Menu,
         ))
         .with_children(|children| {
-            let button_colors = ButtonColors::default();
-            children
-                .spawn((
-                    ButtonBundle {
-                        style: Style {
-                            width: Val::Px(400.0),
-                            height: Val::Px(50.0),
-                            justify_content: JustifyContent::Center,
-                            align_items: AlignItems::Center,
-                            ..Default::default()
-                        },
-                        background_color: button_colors.normal.into(),
-                        ..Default::default()
-                    },
-                    button_colors,
-                    PlayButton,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Play",
-                        TextStyle {
-                            font_size: 40.0,
-                            color: Color::linear_rgb(0.9, 0.9, 0.9),
-                            ..default()
-                        },
-                    ));
-                });
+            for (label, fighter) in [
+                ("Play Samurai", Fighter::Samurai),
+                ("Play Knight", Fighter::Knight),
+            ] {
+                spawn_button(children, label, PlayButton(fighter));
+            }
+            spawn_button(
+                children,
+                friendly_fire_label(friendly_fire.0),
+                FriendlyFireButton,
+            );
+            spawn_button(children, "Controls", ControlsButton);
         });
     commands.spawn((
         NodeBundle {
@@ -107,17 +110,84 @@ fn setup_menu(mut commands: Commands, assets: Res<TextureAssets>) {
     ));
 }
 
+pub fn spawn_button(children: &mut ChildBuilder, label: impl Into<String>, action: impl Bundle) {
+    let button_colors = ButtonColors::default();
+    children
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(400.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::all(Val::Px(5.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                background_color: button_colors.normal.into(),
+                ..Default::default()
+            },
+            button_colors,
+            action,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::linear_rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn friendly_fire_label(on: bool) -> String {
+    format!("Friendly fire: {}", if on { "On" } else { "Off" })
+}
+
 fn click_play_button(
     mut next_state: ResMut<NextState<GameState>>,
+    mut fighter: ResMut<Fighter>,
     mut interaction_query: Query<(&Interaction, &PlayButton)>,
 ) {
-    for (interaction, _) in &mut interaction_query {
+    for (interaction, &PlayButton(picked)) in &mut interaction_query {
         if *interaction == Interaction::Pressed {
+            *fighter = picked;
             next_state.set(GameState::Playing);
         }
     }
 }
 
+fn click_friendly_fire(
+    mut friendly_fire: ResMut<FriendlyFire>,
+    buttons: Query<(&Interaction, &Children), (Changed<Interaction>, With<FriendlyFireButton>)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (interaction, children) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        friendly_fire.0 = !friendly_fire.0;
+        for &child in children {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].value = friendly_fire_label(friendly_fire.0);
+            }
+        }
+    }
+}
+
+fn click_controls_button(
+    mut next_state: ResMut<NextState<GameState>>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<ControlsButton>)>,
+) {
+    if buttons
+        .iter()
+        .any(|&interaction| interaction == Interaction::Pressed)
+    {
+        next_state.set(GameState::Controls);
+    }
+}
+
 fn hover_button(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor, &ButtonColors),
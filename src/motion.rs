@@ -0,0 +1,374 @@
+use std::time::Duration;
+
+use crate::attack::{attack, AttackStep, Attacking, WantsAttack};
+use crate::charge::Charging;
+use crate::dodge::Dodging;
+use crate::guard::Guarding;
+use crate::input::{InputBuffer, Press, RunSwitch};
+use crate::jump::Jumping;
+use crate::player::{
+    ActionSet, Character, Controller, Controller1, Controller2, ReadInputSet, Ready,
+};
+use crate::GameState;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub struct MotionPlugin;
+
+/// This plugin recognises motion inputs in the input buffers, directions relative to the facing ended by a button
+/// Recognised motions are sent as [`SpecialInput`] events, characters map them to their special moves
+/// The special button stands for the special whose motion ends the way the movement is held, or the first one
+impl Plugin for MotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpecialInput>()
+            .init_resource::<MotionWindows>()
+            .add_systems(
+                Update,
+                (recognize::<Controller1>, recognize::<Controller2>)
+                    .in_set(MotionSet)
+                    .in_set(ReadInputSet)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (special::<Controller1>, special::<Controller2>)
+                    .in_set(ActionSet)
+                    .after(ReadInputSet)
+                    .before(attack)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Sends the [`SpecialInput`] events
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MotionSet;
+
+/// How lenient the recognition is
+/// [`Motion::DoubleTapForward`] uses the double tap window of the [`RunSwitch`] of the controller instead
+#[derive(Resource, Clone)]
+pub struct MotionWindows {
+    /// Longest time between two directions of a motion
+    pub step: Duration,
+    /// Longest time between the last direction and the button
+    pub button: Duration,
+}
+
+impl Default for MotionWindows {
+    fn default() -> Self {
+        MotionWindows {
+            step: Duration::from_millis(200),
+            button: Duration::from_millis(250),
+        }
+    }
+}
+
+/// A direction relative to where the character faces
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Way {
+    Neutral,
+    Forward,
+    Back,
+    Up,
+    Down,
+    UpForward,
+    UpBack,
+    DownForward,
+    DownBack,
+}
+
+impl Way {
+    fn of(stick: IVec2, facing: i32) -> Way {
+        match (stick.x * facing, stick.y) {
+            (1, 1) => Way::UpForward,
+            (1, -1) => Way::DownForward,
+            (1, _) => Way::Forward,
+            (-1, 1) => Way::UpBack,
+            (-1, -1) => Way::DownBack,
+            (-1, _) => Way::Back,
+            (_, 1) => Way::Up,
+            (_, -1) => Way::Down,
+            _ => Way::Neutral,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Motion {
+    /// Forward, let go, forward
+    DoubleTapForward,
+    /// Back then forward, and attack
+    BackForward,
+    /// Down then up, and attack
+    DownUp,
+    /// Down, down forward, forward, and attack
+    QuarterCircle,
+}
+
+impl Motion {
+    /// Checked in this order, so a motion is not mistaken for a shorter one it ends with
+    const ALL: [Motion; 4] = [
+        Motion::QuarterCircle,
+        Motion::BackForward,
+        Motion::DownUp,
+        Motion::DoubleTapForward,
+    ];
+
+    /// Directions in order and the button ending the motion
+    fn sequence(self) -> (&'static [Way], Option<Press>) {
+        match self {
+            Motion::DoubleTapForward => (&[Way::Forward, Way::Neutral, Way::Forward], None),
+            Motion::BackForward => (&[Way::Back, Way::Forward], Some(Press::Attack)),
+            Motion::DownUp => (&[Way::Down, Way::Up], Some(Press::Attack)),
+            Motion::QuarterCircle => (
+                &[Way::Down, Way::DownForward, Way::Forward],
+                Some(Press::Attack),
+            ),
+        }
+    }
+
+    /// Way of the last direction of a motion ended by a button
+    fn last_way(self) -> Option<Way> {
+        match self.sequence() {
+            (ways, Some(_)) => ways.last().copied(),
+            (_, None) => None,
+        }
+    }
+
+    /// True when the latest presses, made now, end with the motion
+    /// Directions that are not part of it may come in between as long as the windows are kept
+    fn ends<'a>(
+        self,
+        mut history: impl Iterator<Item = &'a (Press, Duration)>,
+        now: Duration,
+        facing: i32,
+        windows: &MotionWindows,
+    ) -> bool {
+        let (ways, button) = self.sequence();
+        let mut ways = ways.iter().rev().peekable();
+        let (mut last, mut window) = (now, windows.step);
+        if let Some(button) = button {
+            match history.next() {
+                Some(&(press, at)) if press == button && at == now => {}
+                _ => return false,
+            }
+            window = windows.button;
+        }
+
+        for &(press, at) in history {
+            let Press::Stick(stick) = press else {
+                continue;
+            };
+            let Some(&&want) = ways.peek() else {
+                break;
+            };
+            if last.saturating_sub(at) > window {
+                return false;
+            }
+            if Way::of(stick, facing) != want {
+                continue;
+            }
+            // without a button the motion is made by its last direction
+            if button.is_none() && ways.len() == self.sequence().0.len() && at != now {
+                return false;
+            }
+            ways.next();
+            last = at;
+            window = windows.step;
+        }
+        ways.peek().is_none()
+    }
+}
+
+/// A character made a motion input
+#[derive(Event, Debug)]
+pub struct SpecialInput {
+    pub character: Entity,
+    pub motion: Motion,
+    /// Press that ended the motion and when it was made, used up by the special move
+    pub button: Option<(Press, Duration)>,
+}
+
+/// Moves done by motion inputs, a special move cancels a swing past its cancel frame
+#[derive(Component, Clone, Default)]
+pub struct Specials(pub Vec<(Motion, AttackStep)>);
+
+fn recognize<C: Controller>(
+    time: Res<Time>,
+    windows: Res<MotionWindows>,
+    buffer: Res<InputBuffer<C>>,
+    characters: Query<(Entity, &Sprite, Option<&Specials>), (With<C>, With<Character>)>,
+    switches: Query<&RunSwitch, With<C>>,
+    mut specials: EventWriter<SpecialInput>,
+) {
+    let now = time.elapsed();
+    let double_tap = switches
+        .get_single()
+        .map(|switch| MotionWindows {
+            step: switch.double_tap,
+            ..windows.clone()
+        })
+        .unwrap_or_else(|_| windows.clone());
+    let Some(&(latest, at)) = buffer.history().next_back() else {
+        return;
+    };
+    if at != now {
+        return;
+    }
+    for (character, sprite, moves) in &characters {
+        let facing = if sprite.flip_x { -1 } else { 1 };
+        if latest == Press::Special {
+            let way = Way::of(buffer.stick(), facing);
+            let mut motions = moves
+                .iter()
+                .flat_map(|moves| &moves.0)
+                .map(|&(motion, _)| motion)
+                .filter(|motion| motion.last_way().is_some());
+            let first = motions.clone().next();
+            if let Some(motion) = motions
+                .find(|motion| motion.last_way() == Some(way))
+                .or(first)
+            {
+                specials.send(SpecialInput {
+                    character,
+                    motion,
+                    button: Some((Press::Special, at)),
+                });
+            }
+            continue;
+        }
+        let motion = Motion::ALL.into_iter().find(|&motion| {
+            let windows = if motion == Motion::DoubleTapForward {
+                &double_tap
+            } else {
+                &windows
+            };
+            motion.ends(buffer.history().rev(), now, facing, windows)
+        });
+        if let Some(motion) = motion {
+            specials.send(SpecialInput {
+                character,
+                motion,
+                button: motion.sequence().1.map(|press| (press, at)),
+            });
+        }
+    }
+}
+
+/// Runs before [`attack`], the attack press ending a motion starts the special instead of a swing
+fn special<C: Controller>(
+    mut commands: Commands,
+    mut inputs: EventReader<SpecialInput>,
+    mut buffer: ResMut<InputBuffer<C>>,
+    characters: Query<
+        (&Specials, Option<(&Attacking, &TextureAtlas)>),
+        (
+            With<C>,
+            Ready,
+            Without<Guarding>,
+            Without<Dodging>,
+            Without<Jumping>,
+            Without<Charging>,
+        ),
+    >,
+) {
+    for &SpecialInput {
+        character,
+        motion,
+        button,
+    } in inputs.read()
+    {
+        let Ok((specials, attacking)) = characters.get(character) else {
+            continue;
+        };
+        let Some((_, attack)) = specials.0.iter().find(|(m, _)| *m == motion) else {
+            continue;
+        };
+        if attacking.is_some_and(|(attacking, atlas)| atlas.index < attacking.attack.cancel) {
+            continue;
+        }
+        let mut entity = commands.entity(character);
+        entity.remove::<WantsAttack>();
+        Attacking::start(&mut entity, attack.clone(), None);
+        if let Some((button, at)) = button {
+            buffer.consume(button, at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORWARD: Press = Press::Stick(IVec2::new(1, 0));
+    const BACK: Press = Press::Stick(IVec2::new(-1, 0));
+    const DOWN: Press = Press::Stick(IVec2::new(0, -1));
+    const DOWN_FORWARD: Press = Press::Stick(IVec2::new(1, -1));
+    const NEUTRAL: Press = Press::Stick(IVec2::ZERO);
+
+    /// Presses made at the milliseconds, from the oldest to the latest
+    fn history(presses: &[(Press, u64)]) -> Vec<(Press, Duration)> {
+        presses
+            .iter()
+            .map(|&(press, ms)| (press, Duration::from_millis(ms)))
+            .collect()
+    }
+
+    /// True when the motion ends with the latest press, made now, facing right or left
+    fn ends(motion: Motion, presses: &[(Press, u64)], facing: i32) -> bool {
+        let history = history(presses);
+        let now = history.last().map_or(Duration::ZERO, |&(_, at)| at);
+        motion.ends(history.iter().rev(), now, facing, &MotionWindows::default())
+    }
+
+    #[test]
+    fn quarter_circle_ends_with_attack() {
+        let presses = [
+            (DOWN, 0),
+            (DOWN_FORWARD, 50),
+            (FORWARD, 100),
+            (Press::Attack, 150),
+        ];
+        assert!(ends(Motion::QuarterCircle, &presses, 1));
+        assert!(!ends(Motion::QuarterCircle, &presses[..3], 1));
+    }
+
+    #[test]
+    fn directions_are_relative_to_the_facing() {
+        let presses = [(BACK, 0), (FORWARD, 100), (Press::Attack, 150)];
+        assert!(ends(Motion::BackForward, &presses, 1));
+        assert!(!ends(Motion::BackForward, &presses, -1));
+        let mirrored = [(FORWARD, 0), (BACK, 100), (Press::Attack, 150)];
+        assert!(ends(Motion::BackForward, &mirrored, -1));
+    }
+
+    #[test]
+    fn windows_are_kept() {
+        // the button comes too late after the last direction
+        let late_button = [(BACK, 0), (FORWARD, 100), (Press::Attack, 400)];
+        assert!(!ends(Motion::BackForward, &late_button, 1));
+        // the directions are too far apart
+        let late_step = [(BACK, 0), (FORWARD, 300), (Press::Attack, 350)];
+        assert!(!ends(Motion::BackForward, &late_step, 1));
+    }
+
+    #[test]
+    fn other_directions_may_come_in_between() {
+        let presses = [
+            (BACK, 0),
+            (DOWN_FORWARD, 50),
+            (FORWARD, 100),
+            (Press::Attack, 150),
+        ];
+        assert!(ends(Motion::BackForward, &presses, 1));
+    }
+
+    #[test]
+    fn double_tap_ends_with_its_last_direction() {
+        let presses = [(FORWARD, 0), (NEUTRAL, 80), (FORWARD, 160)];
+        assert!(ends(Motion::DoubleTapForward, &presses, 1));
+        // made earlier, the motion was already recognised
+        let earlier = [(FORWARD, 0), (NEUTRAL, 80), (FORWARD, 160), (DOWN, 200)];
+        assert!(!ends(Motion::DoubleTapForward, &earlier, 1));
+    }
+}
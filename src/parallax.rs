@@ -0,0 +1,59 @@
+use crate::camera::{CameraSet, CameraView};
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct ParallaxPlugin;
+
+/// This plugin scrolls the background layers of the level with the camera
+/// Far layers follow the camera and seem to move slower than the stage, repeating layers fill the whole view
+impl Plugin for ParallaxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            scroll
+                .after(CameraSet)
+                .before(TransformSystem::TransformPropagate)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// A background image placed relative to the camera
+#[derive(Component, Clone)]
+pub struct ParallaxLayer {
+    /// How much the layer moves with the stage, 1 is the floor and 0 stays still on the screen
+    pub scroll: f32,
+    /// Repeat the image horizontally to cover the view
+    pub repeat: bool,
+    /// X of the layer when the camera is at the origin
+    pub x: f32,
+}
+
+fn scroll(
+    view: Res<CameraView>,
+    images: Res<Assets<Image>>,
+    mut layers: Query<(&ParallaxLayer, &Handle<Image>, &mut Transform, &mut Sprite)>,
+) {
+    let Some(view) = view.0 else {
+        return;
+    };
+    let camera = view.center().x;
+    for (layer, image, mut t, mut sprite) in &mut layers {
+        let x = layer.x + camera * (1. - layer.scroll);
+        if !layer.repeat {
+            t.translation.x = x;
+            continue;
+        }
+        let Some(size) = images.get(image).map(|image| image.size_f32()) else {
+            continue;
+        };
+        // an odd count of tiles, spanning past both edges of the view, snapped to the image width
+        let step = size.x * t.scale.x;
+        let tiles = (view.width() / step).ceil() as u32 / 2 * 2 + 3;
+        t.translation.x = x + ((camera - x) / step).round() * step;
+        let width = Some(Vec2::new(tiles as f32 * size.x, size.y));
+        if sprite.custom_size != width {
+            sprite.custom_size = width;
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::time::Duration;
+
+use crate::collision::HitSet;
+use crate::health::{Hurt, HurtClips};
+use crate::player::{LevelLimit, Movement};
+use crate::sprite_sheet::{AnimationEnded, NoRepeat};
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+pub struct PhysicsPlugin;
+
+/// This plugin moves characters that were pushed around by hits
+/// Height is a separate axis from the floor position, a character in the air keeps its floor Y
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                knockback.run_if(resource_exists::<LevelLimit>),
+                fall,
+                get_up,
+            )
+                .chain()
+                .after(HitSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(PostUpdate, lift.run_if(in_state(GameState::Playing)));
+    }
+}
+
+const GRAVITY: f32 = 900.;
+/// Deceleration of a knockback slide on the floor
+const FRICTION: f32 = 400.;
+/// Share of the speed kept when bouncing off the level limits
+const WALL_BOUNCE: f32 = 0.5;
+const SPRITE_SIZE: f32 = 128.;
+
+/// Distance between the feet and the floor
+#[derive(Component, Default)]
+pub struct Height(pub f32);
+
+#[derive(Component)]
+pub struct VerticalVelocity(pub f32);
+
+/// Floor velocity given by a hit, slides out on the ground
+#[derive(Component)]
+pub struct Knockback(pub Vec2);
+
+/// Sent flying by a hit, lands into a knockdown
+#[derive(Component)]
+pub struct Launched {
+    pub down: Duration,
+}
+
+#[derive(Component)]
+pub struct KnockedDown {
+    timer: Timer,
+    rising: bool,
+}
+
+fn knockback(
+    mut commands: Commands,
+    time: Res<Time>,
+    limit: Res<LevelLimit>,
+    mut bodies: Query<(
+        Entity,
+        &mut Transform,
+        &mut Knockback,
+        Option<&VerticalVelocity>,
+    )>,
+) {
+    let dt = time.delta_seconds();
+    for (e, mut t, mut knockback, airborne) in &mut bodies {
+        t.translation.x += knockback.0.x * dt;
+        t.translation.y += knockback.0.y * dt;
+
+        if airborne.is_some() {
+            let LevelLimit(bounds) = *limit;
+            if (t.translation.x < bounds.min.x && knockback.0.x < 0.)
+                || (t.translation.x > bounds.max.x && knockback.0.x > 0.)
+            {
+                t.translation.x = t.translation.x.clamp(bounds.min.x, bounds.max.x);
+                knockback.0.x *= -WALL_BOUNCE;
+            }
+            continue;
+        }
+
+        let speed = knockback.0.length() - FRICTION * dt;
+        if speed <= 0. {
+            commands.entity(e).remove::<Knockback>();
+        } else {
+            knockback.0 = knockback.0.normalize() * speed;
+        }
+    }
+}
+
+fn fall(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut bodies: Query<(
+        Entity,
+        &mut Height,
+        &mut VerticalVelocity,
+        Option<&Launched>,
+        Option<&HurtClips>,
+    )>,
+) {
+    let dt = time.delta_seconds();
+    for (e, mut height, mut velocity, launched, clips) in &mut bodies {
+        height.0 += velocity.0 * dt;
+        velocity.0 -= GRAVITY * dt;
+
+        if height.0 > 0. {
+            continue;
+        }
+
+        height.0 = 0.;
+        commands.entity(e).remove::<VerticalVelocity>();
+
+        let (Some(launched), Some(clips)) = (launched, clips) else {
+            continue;
+        };
+        commands
+            .entity(e)
+            .remove::<(Launched, Hurt)>()
+            .insert((
+                KnockedDown {
+                    timer: Timer::new(launched.down, TimerMode::Once),
+                    rising: false,
+                },
+                NoRepeat,
+            ))
+            .insert(clips.dead.bundle());
+    }
+}
+
+fn get_up(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ended: EventReader<AnimationEnded>,
+    mut down: Query<(Entity, &mut KnockedDown, &HurtClips)>,
+) {
+    for &AnimationEnded(e) in ended.read() {
+        let Ok((_, knocked, _)) = down.get(e) else {
+            continue;
+        };
+        if knocked.rising {
+            commands
+                .entity(e)
+                .remove::<(KnockedDown, NoRepeat)>()
+                .insert(Movement::Idle);
+        }
+    }
+
+    for (e, mut knocked, clips) in &mut down {
+        if !knocked.rising && knocked.timer.tick(time.delta()).finished() {
+            knocked.rising = true;
+            commands.entity(e).insert(clips.get_up.bundle());
+        }
+    }
+}
+
+/// Raises the sprite above its floor position, the shadow stays on the floor
+fn lift(mut bodies: Query<(&Height, &mut Sprite), Changed<Height>>) {
+    for (height, mut sprite) in &mut bodies {
+        sprite.anchor = Anchor::Custom(Vec2::new(0., -height.0 / SPRITE_SIZE));
+    }
+}
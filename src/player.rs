@@ -1,8 +1,20 @@
-use crate::assets::SamuraiAssets;
+use crate::assets::CharacterAssets;
+use crate::attack::Attacking;
+use crate::bindings::Bindings;
+use crate::camera::CameraView;
+use crate::character::CharacterDef;
+use crate::charge::Charging;
+use crate::depth::YSort;
+use crate::dodge::Dodging;
+use crate::encounter::{Entrance, ScreenLock};
+use crate::enemy::Enemy;
+use crate::guard::{Dizzy, GuardMeter, Guarding};
+use crate::health::{Health, Hurt};
 use crate::input::Active;
-use crate::sprite_sheet::{
-    self, Animation, AnimationEnded, AnimationTimer, NoRepeat, SpriteAnimation,
-};
+use crate::jump::Jumping;
+use crate::level::Level;
+use crate::physics::{Height, KnockedDown};
+use crate::sprite_sheet::{self, AnimationEnded, AnimationTimer, Clip, NoRepeat, SpriteAnimation};
 use crate::{input, GameState};
 use bevy::ecs::world::Command;
 use bevy::input::keyboard::KeyboardInput;
@@ -13,44 +25,98 @@ use rand::Rng;
 
 pub struct PlayerPlugin;
 
-trait Controller: Component + Default {}
+pub trait Controller: Component + Default {}
 
 #[derive(Default, Component)]
 pub struct Controller1;
 
 impl Controller for Controller1 {}
 
-#[cfg_attr(Default, Controller, derive(Controller))]
+#[derive(Default, Component)]
 pub struct Controller2;
 
-#[derive(Component)]
-pub struct Character;
+impl Controller for Controller2 {}
 
 #[derive(Component)]
-pub struct Samurai;
+pub struct Character;
 
 #[derive(Component)]
 pub struct Alive;
 
+/// The character the player picked in the menu
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fighter {
+    #[default]
+    Samurai,
+    Knight,
+}
+
+impl Fighter {
+    /// The character the second player gets, the one the first player didn't pick
+    pub fn other(self) -> Fighter {
+        match self {
+            Fighter::Samurai => Fighter::Knight,
+            Fighter::Knight => Fighter::Samurai,
+        }
+    }
+
+    pub fn def(self, characters: &CharacterAssets) -> Handle<CharacterDef> {
+        match self {
+            Fighter::Samurai => characters.samurai.clone(),
+            Fighter::Knight => characters.knight.clone(),
+        }
+    }
+}
+
+/// Characters that can start an action, possibly cancelling their current attack
+pub type Ready = (
+    With<Alive>,
+    Without<Hurt>,
+    Without<KnockedDown>,
+    Without<Dizzy>,
+);
+/// Characters that are not busy with an action or a reaction
+pub type Free = (
+    Ready,
+    Without<Attacking>,
+    Without<Guarding>,
+    Without<Dodging>,
+    Without<Jumping>,
+);
+
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
-struct ReadInputSet;
+pub struct ReadInputSet;
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
-struct ActionSet;
+pub struct ActionSet;
 
-#[derive(Component)]
-pub struct LevelLimit;
+/// Distance kept between the players and the edges of the view
+const VIEW_MARGIN: f32 = 16.;
+/// Distance from the center of a character sprite down to its feet
+const FEET: f32 = 64.;
 
-#[derive(Component)]
+/// Bounds of the floor characters can walk on, inserted from the level once it is built
+/// Systems keeping characters on the floor wait for it
+#[derive(Resource, Clone, Copy)]
+pub struct LevelLimit(pub Rect);
+
+#[derive(Component, Clone)]
 pub struct MoveSpeed {
-    walk: f32,
-    run: f32,
+    pub walk: f32,
+    pub run: f32,
+}
+
+#[derive(Component, Clone)]
+pub struct MovementClips {
+    pub idle: Clip,
+    pub walk: Clip,
+    pub run: Clip,
 }
 
 #[derive(Component)]
 pub struct Speed(f32);
 
-#[derive(Component)]
-pub struct Direction(f32, f32);
+#[derive(Component, PartialEq)]
+pub struct Direction(pub f32, pub f32);
 
 #[derive(Component, Eq, PartialEq, Copy, Clone, Debug)]
 pub enum Movement {
@@ -63,64 +129,141 @@ pub enum Movement {
 /// Player logic is only active during the State `GameState::Playing`
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), init)
+        app.init_resource::<Fighter>()
+            .add_systems(OnEnter(GameState::Playing), init)
             .add_systems(
                 Update,
-                (set_direction::<Controller1>, set_movement::<Controller1>)
+                (
+                    join,
+                    set_direction::<Controller1>,
+                    set_movement::<Controller1>,
+                    set_direction::<Controller2>,
+                    set_movement::<Controller2>,
+                )
                     .in_set(ReadInputSet)
                     .run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 Update,
-                (limit, movement)
+                (limit.run_if(resource_exists::<LevelLimit>), movement)
                     .in_set(ActionSet)
                     .after(ReadInputSet)
                     .run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 PostUpdate,
-                (flip_x, init_samurai, init_shadow, movement_animation)
+                (flip_x, init_character, init_shadow, movement_animation)
                     .run_if(in_state(GameState::Playing)),
             );
     }
 }
 
-fn init(mut commands: Commands) {
-    commands.spawn((Name::new("Player"), Character, Samurai, Controller1));
+fn init(
+    mut commands: Commands,
+    bindings: Res<Bindings>,
+    fighter: Res<Fighter>,
+    characters: Res<CharacterAssets>,
+    level: Level,
+) {
+    let def = fighter.def(&characters);
+    let spawn = level
+        .def()
+        .and_then(|level| level.spawns.first().copied())
+        .unwrap_or_default();
+    commands.spawn((
+        Name::new("Player 1"),
+        Character,
+        Controller1,
+        def,
+        Transform::from_xyz(spawn.x, spawn.y, 10.),
+    ));
+
+    bindings.spawn(&mut commands);
 }
 
-fn init_samurai(mut commands: Commands, players: Query<Entity, Added<Samurai>>) {
-    for id in &players {
-        commands.entity(id).insert((
-            MoveSpeed {
-                walk: 80.,
-                run: 200.,
-            },
+/// The second player joins by pressing attack, next to the first one with the other character
+fn join(
+    mut commands: Commands,
+    fighter: Res<Fighter>,
+    characters: Res<CharacterAssets>,
+    level: Level,
+    joined: Query<(), (With<Controller2>, With<Character>)>,
+    first: Query<&Transform, (With<Controller1>, With<Character>)>,
+    pressed: Query<(), (With<Controller2>, With<input::Attack>, With<input::Just>)>,
+) {
+    if pressed.is_empty() || !joined.is_empty() {
+        return;
+    }
+    let spawn = level
+        .def()
+        .and_then(|level| level.spawns.get(1).copied())
+        .unwrap_or_default();
+    let x = first
+        .get_single()
+        .map(|t| t.translation.x)
+        .unwrap_or(spawn.x);
+    commands.spawn((
+        Name::new("Player 2"),
+        Character,
+        Controller2,
+        fighter.other().def(&characters),
+        Transform::from_xyz(x, spawn.y, 10.),
+    ));
+}
+
+fn init_character(
+    mut commands: Commands,
+    players: Query<(Entity, &Handle<CharacterDef>, &Transform), Added<Handle<CharacterDef>>>,
+    defs: Res<Assets<CharacterDef>>,
+) {
+    for (id, handle, spawn) in &players {
+        let Some(def) = defs.get(handle) else {
+            continue;
+        };
+        // the spawner places the character, the sprite bundle would reset it
+        let spawn = spawn.translation.truncate();
+        let mut player = commands.entity(id);
+        player.insert((
+            def.attacks.clone(),
+            Health::new(def.health),
+            def.hurt.clone(),
+            def.hurtbox.clone(),
+            def.movement.clone(),
+            def.speed.clone(),
+            Height::default(),
+            YSort { feet: -FEET },
             Movement::Idle,
             Alive,
             Direction(1., 0.),
             SpriteBundle {
-                transform: Transform::from_xyz(-200., 0., 10.),
+                transform: Transform::from_xyz(spawn.x, spawn.y, 10.),
                 ..default()
             },
         ));
-
-        commands.spawn((
-            Controller1,
-            input::Analog(0., 0.),
-            input::Movement,
-            input::KeyboardAnalog(KeyCode::KeyW, KeyCode::KeyS, KeyCode::KeyD, KeyCode::KeyA),
-        ));
-        commands.spawn((
-            Controller1,
-            input::Run,
-            input::KeyboardAction(KeyCode::ShiftLeft),
-        ));
-        commands.spawn((
-            Controller1,
-            input::Attack,
-            input::MouseAction(MouseButton::Left),
-        ));
+        if let Some(charge) = &def.charge {
+            player.insert(charge.clone());
+        }
+        if let Some(run_attack) = &def.run_attack {
+            player.insert(run_attack.clone());
+        }
+        if let Some(jump) = &def.jump {
+            player.insert(jump.clone());
+        }
+        if let Some(jump_attack) = &def.jump_attack {
+            player.insert(jump_attack.clone());
+        }
+        if !def.specials.0.is_empty() {
+            player.insert(def.specials.clone());
+        }
+        if let Some(guard_attack) = &def.guard_attack {
+            player.insert(guard_attack.clone());
+        }
+        if let Some(guard) = &def.guard {
+            player.insert((guard.clone(), GuardMeter(guard.meter)));
+        }
+        if let Some(dodge) = &def.dodge {
+            player.insert(dodge.clone());
+        }
     }
 }
 
@@ -141,7 +284,7 @@ fn init_shadow(
                 MaterialMesh2dBundle {
                     mesh: shadow,
                     material: color,
-                    transform: Transform::from_xyz(0., -64., 0.),
+                    transform: Transform::from_xyz(0., -FEET, 0.),
                     ..default()
                 },
             ));
@@ -151,7 +294,7 @@ fn init_shadow(
 
 fn set_direction<C: Controller>(
     mut commands: Commands,
-    player: Query<Entity, (With<C>, With<Character>)>,
+    player: Query<Entity, (With<C>, With<Character>, Free)>,
     movement: Query<&input::Analog, (With<input::Active>, With<C>, With<input::Movement>)>,
 ) {
     let Ok(player) = player.get_single() else {
@@ -171,11 +314,11 @@ fn set_direction<C: Controller>(
 
 fn set_movement<C: Controller>(
     mut commands: Commands,
-    player: Query<(Entity, &Movement), With<C>>,
+    player: Query<(Entity, &Movement, Has<Charging>), (With<C>, Free)>,
     input: Query<Entity, (With<input::Active>, With<C>, With<input::Movement>)>,
-    is_run: Query<(&input::Run, &Active)>,
+    is_run: Query<(), (With<input::RunSwitch>, With<Active>, With<C>)>,
 ) {
-    let Ok((entity, &movement)) = player.get_single() else {
+    let Ok((entity, &movement, charging)) = player.get_single() else {
         return;
     };
 
@@ -183,7 +326,7 @@ fn set_movement<C: Controller>(
         if movement != Movement::Idle {
             commands.entity(entity).insert(Movement::Idle);
         }
-    } else if is_run.is_empty() {
+    } else if is_run.is_empty() || charging {
         if movement != Movement::Walk {
             commands.entity(entity).insert(Movement::Walk);
         }
@@ -194,11 +337,11 @@ fn set_movement<C: Controller>(
 
 fn movement(
     time: Res<Time>,
-    mut players: Query<(&mut Transform, &Movement, &MoveSpeed, &Direction)>,
+    mut players: Query<(&mut Transform, &Movement, &MoveSpeed, &Direction), Free>,
 ) {
     for (mut t, movement, speed, &Direction(x, y)) in &mut players {
         if movement == &Movement::Idle {
-            return;
+            continue;
         }
 
         let speed = if movement == &Movement::Walk {
@@ -219,51 +362,54 @@ fn flip_x(mut sprites: Query<(&mut Sprite, &Direction), Changed<Direction>>) {
     }
 }
 
-fn limit(mut players: Query<&mut Transform, With<Character>>) {
-    for mut t in &mut players {
-        if t.translation.y > 35. {
-            t.translation.y = 35.;
+fn limit(
+    limit: Res<LevelLimit>,
+    lock: Res<ScreenLock>,
+    view: Res<CameraView>,
+    mut players: Query<(&mut Transform, Has<Enemy>), (With<Character>, Without<Entrance>)>,
+) {
+    let LevelLimit(stage) = *limit;
+    for (mut t, enemy) in &mut players {
+        let mut bounds = stage;
+        // players can't walk out of the view, enemies may wander behind the camera
+        if !enemy {
+            if let Some((left, right)) = lock.0 {
+                bounds.min.x = bounds.min.x.max(left);
+                bounds.max.x = bounds.max.x.min(right);
+            }
+            if let Some(view) = view.0 {
+                bounds.min.x = bounds.min.x.max(view.min.x + VIEW_MARGIN);
+                bounds.max.x = bounds.max.x.min(view.max.x - VIEW_MARGIN);
+            }
         }
-        if t.translation.y < -40. {
-            t.translation.y = -40.;
+        if t.translation.y > bounds.max.y {
+            t.translation.y = bounds.max.y;
         }
-        if t.translation.x < -200. {
-            t.translation.x = -200.;
+        if t.translation.y < bounds.min.y {
+            t.translation.y = bounds.min.y;
         }
-        if t.translation.x > 200. {
-            t.translation.x = 200.;
+        if t.translation.x < bounds.min.x {
+            t.translation.x = bounds.min.x;
+        }
+        if t.translation.x > bounds.max.x {
+            t.translation.x = bounds.max.x;
         }
     }
 }
 
 fn movement_animation(
     mut commands: Commands,
-    players: Query<(Entity, &Movement), (With<Samurai>, Changed<Movement>)>,
-    assets: Res<SamuraiAssets>,
+    players: Query<
+        (Entity, &Movement, &MovementClips),
+        (Changed<Movement>, Free, Without<Charging>),
+    >,
 ) {
-    for (e, &movement) in &players {
-        match movement {
-            Movement::Idle => {
-                commands.entity(e).insert((
-                    assets.idle.clone(),
-                    TextureAtlas::from(assets.idle_layout.clone()),
-                    Animation::new(1000, 0, 3),
-                ));
-            }
-            Movement::Walk => {
-                commands.entity(e).insert((
-                    assets.walk.clone(),
-                    TextureAtlas::from(assets.walk_layout.clone()),
-                    Animation::new(1000, 0, 8),
-                ));
-            }
-            Movement::Run => {
-                commands.entity(e).insert((
-                    assets.run.clone(),
-                    TextureAtlas::from(assets.run_layout.clone()),
-                    Animation::new(600, 0, 7),
-                ));
-            }
-        }
+    for (e, &movement, clips) in &players {
+        let clip = match movement {
+            Movement::Idle => &clips.idle,
+            Movement::Walk => &clips.walk,
+            Movement::Run => &clips.run,
+        };
+        commands.entity(e).insert(clip.bundle());
     }
 }
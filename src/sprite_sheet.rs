@@ -37,12 +37,45 @@ pub struct Animation {
 impl Animation {
     pub fn new(duration: u64, start: usize, end: usize) -> Animation {
         Animation {
-            timer: AnimationTimer::new(duration / (end - start) as u64),
+            timer: AnimationTimer::new(duration / (end - start).max(1) as u64),
             index: AnimationIndex::new(start, end),
         }
     }
 }
 
+/// A single row texture atlas played from its first frame
+#[derive(Clone)]
+pub struct Clip {
+    pub texture: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub duration: u64,
+    pub frames: usize,
+}
+
+impl Clip {
+    pub fn new(
+        texture: Handle<Image>,
+        layout: Handle<TextureAtlasLayout>,
+        duration: u64,
+        frames: usize,
+    ) -> Clip {
+        Clip {
+            texture,
+            layout,
+            duration,
+            frames,
+        }
+    }
+
+    pub fn bundle(&self) -> (Handle<Image>, TextureAtlas, Animation) {
+        (
+            self.texture.clone(),
+            TextureAtlas::from(self.layout.clone()),
+            Animation::new(self.duration, 0, self.frames - 1),
+        )
+    }
+}
+
 pub trait SpriteAnimation {
     fn components() -> (Handle<Image>, Handle<TextureAtlasLayout>, Animation);
 }
@@ -51,7 +84,8 @@ pub struct SpriteSheetPlugin;
 
 impl Plugin for SpriteSheetPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, animate)
+        // after Update so ended events always refer to the clip that was set this frame
+        app.add_systems(PostUpdate, animate)
             .add_event::<AnimationEnded>();
     }
 }
@@ -74,7 +108,7 @@ fn animate(
 
             if next > indices.end && norepeat.is_some() {
                 ended.send(AnimationEnded(entity));
-                return;
+                continue;
             }
             if next > indices.end {
                 next = indices.start;
@@ -0,0 +1,276 @@
+use crate::input::{self, TouchButton, TouchJoystick};
+use crate::player::Controller1;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowResized};
+
+pub struct TouchPlugin;
+
+/// This plugin puts touch controls for the first player on screen, a joystick on the left and action buttons on the right
+/// They are shown on phones and tablets, a [`TouchLayout`] inserted before the plugin changes where and how they are drawn
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchLayout>()
+            .add_systems(OnEnter(GameState::Playing), setup_touch)
+            .add_systems(
+                Update,
+                (place_controls, show_controls)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+const JOYSTICK_RADIUS: f32 = 96.;
+const BUTTON_RADIUS: f32 = 48.;
+/// Size of the knob of the joystick, in joystick radiuses
+const KNOB: f32 = 0.4;
+const DEADZONE: f32 = 0.25;
+const CONTROL_COLOR: Color = Color::linear_rgb(0.9, 0.9, 0.9);
+
+/// Where the touch controls are and how they look
+#[derive(Resource, Clone)]
+pub struct TouchLayout {
+    /// Shows the controls, on by default on phones and tablets
+    pub enabled: bool,
+    /// Scale of the controls and of their distances to the corners
+    pub size: f32,
+    /// Opacity of the controls when not pressed
+    pub opacity: f32,
+    /// Center of the joystick, from the bottom left corner of the screen
+    pub joystick: Vec2,
+    /// Centers of the buttons, from the bottom right corner of the screen
+    pub attack: Vec2,
+    pub run: Vec2,
+    pub dodge: Vec2,
+    pub special: Vec2,
+}
+
+impl Default for TouchLayout {
+    fn default() -> Self {
+        TouchLayout {
+            enabled: cfg!(any(target_os = "android", target_os = "ios")),
+            size: 1.,
+            opacity: 0.35,
+            joystick: Vec2::new(170., 170.),
+            attack: Vec2::new(120., 120.),
+            run: Vec2::new(230., 220.),
+            dodge: Vec2::new(240., 100.),
+            special: Vec2::new(100., 240.),
+        }
+    }
+}
+
+impl TouchLayout {
+    /// Center of the control in logical window pixels from the top left corner, like touch positions
+    fn center(&self, control: TouchControl, screen: Vec2) -> Vec2 {
+        let (offset, right) = match control {
+            TouchControl::Joystick => (self.joystick, false),
+            TouchControl::Attack => (self.attack, true),
+            TouchControl::Run => (self.run, true),
+            TouchControl::Dodge => (self.dodge, true),
+            TouchControl::Special => (self.special, true),
+        };
+        let offset = offset * self.size;
+        let x = if right { screen.x - offset.x } else { offset.x };
+        Vec2::new(x, screen.y - offset.y)
+    }
+
+    fn radius(&self, control: TouchControl) -> f32 {
+        let radius = match control {
+            TouchControl::Joystick => JOYSTICK_RADIUS,
+            _ => BUTTON_RADIUS,
+        };
+        radius * self.size
+    }
+
+    /// Opacity of a control, pressed ones stand out
+    fn alpha(&self, pressed: bool) -> f32 {
+        if pressed {
+            (self.opacity * 2.).min(1.)
+        } else {
+            self.opacity
+        }
+    }
+}
+
+/// A touch control, its node is the [`TouchJoystick`] or [`TouchButton`] input
+#[derive(Component, Clone, Copy)]
+enum TouchControl {
+    Joystick,
+    Attack,
+    Run,
+    Dodge,
+    Special,
+}
+
+impl TouchControl {
+    fn label(self) -> &'static str {
+        match self {
+            TouchControl::Joystick => "",
+            TouchControl::Attack => "Attack",
+            TouchControl::Run => "Run",
+            TouchControl::Dodge => "Dodge",
+            TouchControl::Special => "Special",
+        }
+    }
+}
+
+/// Follows the touch on the joystick
+#[derive(Component)]
+struct Knob;
+
+fn control_node() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        border_radius: BorderRadius::MAX,
+        background_color: CONTROL_COLOR.into(),
+        ..default()
+    }
+}
+
+fn setup_touch(mut commands: Commands, layout: Res<TouchLayout>) {
+    if !layout.enabled {
+        return;
+    }
+
+    commands
+        .spawn((
+            Name::new("Touch joystick"),
+            TouchControl::Joystick,
+            Controller1,
+            input::Input,
+            input::Analog(0., 0.),
+            input::Movement,
+            TouchJoystick::new(DEADZONE),
+            control_node(),
+        ))
+        .with_children(|joystick| {
+            joystick.spawn((
+                Knob,
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    border_radius: BorderRadius::MAX,
+                    background_color: CONTROL_COLOR.into(),
+                    ..default()
+                },
+            ));
+        });
+
+    spawn_button(&mut commands, TouchControl::Attack, input::Attack);
+    spawn_button(&mut commands, TouchControl::Run, input::Run);
+    spawn_button(&mut commands, TouchControl::Dodge, input::Dodge);
+    spawn_button(&mut commands, TouchControl::Special, input::Special);
+}
+
+fn spawn_button(commands: &mut Commands, control: TouchControl, action: impl Bundle) {
+    commands
+        .spawn((
+            Name::new("Touch button"),
+            control,
+            Controller1,
+            input::Input,
+            action,
+            TouchButton::default(),
+            control_node(),
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                control.label(),
+                TextStyle {
+                    font_size: 18.,
+                    color: Color::BLACK,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Lays the controls out when they are added, the layout changes or the window is resized
+fn place_controls(
+    layout: Res<TouchLayout>,
+    mut resized: EventReader<WindowResized>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut controls: Query<(
+        Ref<TouchControl>,
+        &mut Style,
+        Option<&mut TouchJoystick>,
+        Option<&mut TouchButton>,
+    )>,
+) {
+    let resized = resized.read().count() > 0;
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let screen = Vec2::new(window.width(), window.height());
+    for (control, mut style, joystick, button) in &mut controls {
+        if !resized && !layout.is_changed() && !control.is_added() {
+            continue;
+        }
+        let radius = layout.radius(*control);
+        let center = layout.center(*control, screen);
+        style.left = Val::Px(center.x - radius);
+        style.top = Val::Px(center.y - radius);
+        style.width = Val::Px(radius * 2.);
+        style.height = Val::Px(radius * 2.);
+        if let Some(mut joystick) = joystick {
+            joystick.center = center;
+            joystick.radius = radius;
+        }
+        if let Some(mut button) = button {
+            button.center = center;
+            button.radius = radius;
+        }
+    }
+}
+
+/// Moves the knob with the joystick and brightens the pressed controls
+fn show_controls(
+    layout: Res<TouchLayout>,
+    mut controls: Query<(
+        &TouchControl,
+        &mut BackgroundColor,
+        Has<input::Active>,
+        Option<Ref<input::Analog>>,
+        &Children,
+    )>,
+    mut knobs: Query<(&mut Style, &mut BackgroundColor), (With<Knob>, Without<TouchControl>)>,
+    mut labels: Query<&mut Text>,
+) {
+    for (&control, mut color, pressed, analog, children) in &mut controls {
+        let alpha = layout.alpha(pressed);
+        color.set_if_neq(CONTROL_COLOR.with_alpha(alpha).into());
+        for &child in children {
+            if let Ok(mut text) = labels.get_mut(child) {
+                let label = Color::BLACK.with_alpha(layout.alpha(true));
+                if text.sections[0].style.color != label {
+                    text.sections[0].style.color = label;
+                }
+            }
+            let (Ok((mut style, mut knob_color)), Some(analog)) = (knobs.get_mut(child), &analog)
+            else {
+                continue;
+            };
+            knob_color.set_if_neq(CONTROL_COLOR.with_alpha(layout.alpha(true)).into());
+            if !analog.is_changed() && !layout.is_changed() {
+                continue;
+            }
+            let input::Analog(x, y) = **analog;
+            let radius = layout.radius(control);
+            let knob = radius * KNOB;
+            // screen Y points down
+            style.left = Val::Px(radius - knob + x * radius);
+            style.top = Val::Px(radius - knob - y * radius);
+            style.width = Val::Px(knob * 2.);
+            style.height = Val::Px(knob * 2.);
+        }
+    }
+}